@@ -0,0 +1,221 @@
+// SPDX-FileCopyrightText: 2025 Alec Delaney
+// SPDX-License-Identifier: MIT
+
+use std::fs;
+use std::path::{absolute, Path, PathBuf};
+
+use crate::board::find_board_for_path;
+use crate::filetree::get_backup_dir;
+use crate::link::{FileIoError, FileOperation};
+
+/// Gets the backup path for a file on the board with the given UID, given its path relative to
+/// the root of the board
+///
+/// The versions of the backup are saved alongside this path, with the version number appended.
+pub fn get_backup_path(uid: &str, relative_path: &Path) -> PathBuf {
+    get_backup_dir().join(uid).join(relative_path)
+}
+
+/// Gets the path of the given version of the backup at the given backup path
+fn get_version_path(backup_path: &Path, version: u32) -> PathBuf {
+    let mut filename = backup_path.file_name().unwrap_or_default().to_os_string();
+    filename.push(format!(".{version}"));
+    backup_path.with_file_name(filename)
+}
+
+/// Gets the versions of the backup at the given backup path, in the order they were saved
+pub fn versions(backup_path: &Path) -> Vec<u32> {
+    let (Some(directory), Some(filename)) = (backup_path.parent(), backup_path.file_name()) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.", filename.to_string_lossy());
+    let mut versions: Vec<u32> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.strip_prefix(&prefix)?.parse().ok()
+        })
+        .collect();
+    versions.sort_unstable();
+    versions
+}
+
+/// Saves a copy of the given file as the next version of the backup at the given backup path,
+/// returning the version saved
+pub fn save(file: &Path, backup_path: &Path) -> Result<u32, FileIoError> {
+    let version = versions(backup_path)
+        .last()
+        .map_or(1, |version| version + 1);
+    let version_path = get_version_path(backup_path, version);
+    if let Some(parent) = version_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|error| FileIoError::new(FileOperation::CreateDirectory, parent, &error))?;
+    }
+    fs::copy(file, &version_path)
+        .map_err(|error| FileIoError::new(FileOperation::Write, &version_path, &error))?;
+    Ok(version)
+}
+
+/// Restores the given version of the backup at the given backup path to the given file, or the
+/// latest version if none is given, returning the version restored
+///
+/// If the file exists and its contents are not already backed up, it is saved as a new version
+/// of the backup first, so that restoring can be undone.
+pub fn restore_from(
+    backup_path: &Path,
+    file: &Path,
+    version: Option<u32>,
+) -> Result<u32, RestoreError> {
+    // Find the requested version of the backup
+    let available = versions(backup_path);
+    let version = match version {
+        Some(version) if available.contains(&version) => version,
+        Some(version) => return Err(RestoreError::NoSuchVersion(version, available)),
+        None => *available.last().ok_or(RestoreError::NoBackups)?,
+    };
+    let version_path = get_version_path(backup_path, version);
+
+    // Keep the current contents of the file before restoring the backup over it
+    if let Ok(current) = fs::read(file) {
+        let is_backed_up = available.iter().any(|version| {
+            fs::read(get_version_path(backup_path, *version)).is_ok_and(|saved| saved == current)
+        });
+        if !is_backed_up {
+            save(file, backup_path)?;
+        }
+    }
+
+    // Copy the backup to the file
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|error| FileIoError::new(FileOperation::CreateDirectory, parent, &error))?;
+    }
+    fs::copy(&version_path, file)
+        .map_err(|error| FileIoError::new(FileOperation::Write, file, &error))?;
+    Ok(version)
+}
+
+/// Backup restoration errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestoreError {
+    /// There are no backups of the file
+    NoBackups,
+    /// The requested version does not exist, along with the versions that do
+    NoSuchVersion(u32, Vec<u32>),
+    /// The backup could not be restored
+    FileIOError(FileIoError),
+}
+
+impl From<FileIoError> for RestoreError {
+    fn from(error: FileIoError) -> Self {
+        RestoreError::FileIOError(error)
+    }
+}
+
+/// Restore the backup of the given file on a connected CircuitPython board
+pub fn restore(path: &Path, version: Option<u32>) -> Result<String, String> {
+    // Find the board the file is on, and the path of the file relative to its root
+    let file = absolute(path).expect("Could not get the absolute path");
+    let Some(board) = find_board_for_path(&file).map_err(|error| error.to_string())? else {
+        return Err(format!(
+            "{} is not on a CircuitPython board",
+            path.display()
+        ));
+    };
+    let Some(uid) = &board.uid else {
+        return Err(format!("{board} does not have a UID to find backups with"));
+    };
+    let relative_path = file
+        .strip_prefix(&board.mount_point)
+        .expect("Could not get the path relative to the board");
+
+    // Restore the backup
+    let backup_path = get_backup_path(uid, relative_path);
+    match restore_from(&backup_path, &file, version) {
+        Ok(version) => Ok(format!(
+            "Restored version {version} of {}",
+            relative_path.display()
+        )),
+        Err(RestoreError::NoBackups) => Err(format!(
+            "There are no backups of {}",
+            relative_path.display()
+        )),
+        Err(RestoreError::NoSuchVersion(version, available)) => {
+            let available: Vec<String> = available.iter().map(u32::to_string).collect();
+            Err(format!(
+                "Version {version} does not exist, available versions are {}",
+                available.join(", ")
+            ))
+        }
+        Err(RestoreError::FileIOError(error)) => Err(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use tempfile::TempDir;
+
+    /// Tests that saved versions are numbered in order, without being confused with the
+    /// versions of other files
+    #[test]
+    fn save() {
+        let directory = TempDir::new().expect("Could not create temporary directory");
+        let file = directory.path().join("code.py");
+        let backup_path = directory.path().join("backups/uid/code.py");
+        let other_backup_path = directory.path().join("backups/uid/code.py.1");
+
+        fs::write(&file, "first").expect("Could not write file");
+        assert_eq!(super::save(&file, &backup_path), Ok(1));
+        fs::write(&file, "second").expect("Could not write file");
+        assert_eq!(super::save(&file, &backup_path), Ok(2));
+        assert_eq!(super::save(&file, &other_backup_path), Ok(1));
+
+        assert_eq!(versions(&backup_path), vec![1, 2]);
+        assert_eq!(versions(&other_backup_path), vec![1]);
+        let contents =
+            fs::read_to_string(get_version_path(&backup_path, 1)).expect("Could not read backup");
+        assert_eq!(contents, "first");
+    }
+
+    /// Tests restoring the latest and specific versions of a backup, keeping the current
+    /// contents of the file as a new version
+    #[test]
+    fn restore_from() {
+        let directory = TempDir::new().expect("Could not create temporary directory");
+        let file = directory.path().join("code.py");
+        let backup_path = directory.path().join("backups/uid/code.py");
+
+        // Check that restoring fails without backups
+        let error =
+            super::restore_from(&backup_path, &file, None).expect_err("Restored without backups");
+        assert_eq!(error, RestoreError::NoBackups);
+
+        // Save two versions, and overwrite the file
+        for contents in ["first", "second"] {
+            fs::write(&file, contents).expect("Could not write file");
+            super::save(&file, &backup_path).expect("Could not save backup");
+        }
+        fs::write(&file, "third").expect("Could not write file");
+
+        // Restore the latest version, which keeps the overwritten contents as a new version
+        assert_eq!(super::restore_from(&backup_path, &file, None), Ok(2));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "second");
+        assert_eq!(versions(&backup_path), vec![1, 2, 3]);
+
+        // Restore a specific version
+        assert_eq!(super::restore_from(&backup_path, &file, Some(1)), Ok(1));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "first");
+        assert_eq!(versions(&backup_path), vec![1, 2, 3]);
+
+        // Check that restoring a nonexistent version fails
+        let error = super::restore_from(&backup_path, &file, Some(7))
+            .expect_err("Restored a nonexistent version");
+        assert_eq!(error, RestoreError::NoSuchVersion(7, vec![1, 2, 3]));
+    }
+}
@@ -1,65 +1,1013 @@
 // SPDX-FileCopyrightText: 2025 Alec Delaney
 // SPDX-License-Identifier: MIT
 
-use std::path::PathBuf;
+use std::fmt;
+use std::fs;
+use std::path::{absolute, Path, PathBuf};
 
-use sysinfo::Disks;
+use serde::{Deserialize, Serialize};
+use sysinfo::{Disk, Disks};
+use tabled::{builder::Builder, Table};
 
-/// Find the connected CircuitPython board.
+use crate::filetree::get_detection_config_file;
+use crate::monitor::FileMonitor;
+use crate::mpy::mpy_version_for;
+
+/// The name of the file CircuitPython writes to the root of the board on boot
+pub const BOOT_OUT_FILENAME: &str = "boot_out.txt";
+
+/// The volume label CircuitPython boards use by default
+pub const DEFAULT_VOLUME_LABEL: &str = "CIRCUITPY";
+
+/// Board detection config errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectionRulesError {
+    /// The detection config file could not be read
+    Unreadable,
+    /// The detection config file is not formatted as expected
+    UnexpectedFormat,
+}
+
+impl fmt::Display for DetectionRulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let config_file = get_detection_config_file();
+        match self {
+            DetectionRulesError::Unreadable => write!(
+                f,
+                "Could not read the board detection config file {}",
+                config_file.display()
+            ),
+            DetectionRulesError::UnexpectedFormat => write!(
+                f,
+                "Could not parse the format of the board detection config file {}",
+                config_file.display()
+            ),
+        }
+    }
+}
+
+/// Rules for detecting connected CircuitPython boards
+///
+/// These can be customized using a JSON file in the application directory, where
+/// any omitted rules use their defaults:
+///
+/// ```json
+/// {
+///   "volume_labels": ["CIRCUITPY", "MYBOARD"],
+///   "marker_files": ["boot_out.txt", "settings.toml"],
+///   "mount_points": ["/home/user/fakeboard"]
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct DetectionRules {
+    /// Volume labels that mark a disk as a board
+    pub volume_labels: Vec<String>,
+    /// Files whose presence at the root of a disk marks it as a board
+    pub marker_files: Vec<String>,
+    /// The only locations allowed to be boards, if any are given, which may also be
+    /// plain directories standing in for boards
+    pub mount_points: Vec<PathBuf>,
+}
+
+impl Default for DetectionRules {
+    fn default() -> Self {
+        DetectionRules {
+            volume_labels: vec![String::from(DEFAULT_VOLUME_LABEL)],
+            marker_files: vec![String::from(BOOT_OUT_FILENAME)],
+            mount_points: Vec::new(),
+        }
+    }
+}
+
+impl DetectionRules {
+    /// Read the detection rules from the given config file
+    pub fn from_file(path: &Path) -> Result<Self, DetectionRulesError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return Err(DetectionRulesError::Unreadable),
+        };
+        match serde_json::from_str(&contents) {
+            Ok(rules) => Ok(rules),
+            Err(_) => Err(DetectionRulesError::UnexpectedFormat),
+        }
+    }
+
+    /// Read the detection rules from the application directory, using the default rules
+    /// if there is no config file
+    pub fn load() -> Result<Self, DetectionRulesError> {
+        let config_file = get_detection_config_file();
+        if !config_file.exists() {
+            return Ok(DetectionRules::default());
+        }
+        DetectionRules::from_file(&config_file)
+    }
+
+    /// Checks whether the given location is a board, given the volume labels of its disk
+    ///
+    /// Locations that are not disk mount points have no volume labels, so can only be
+    /// detected using the marker files.
+    pub fn is_board(&self, mount_point: &Path, labels: &[&str]) -> bool {
+        // If mount points are given, only they are allowed to be boards
+        if !self.mount_points.is_empty() {
+            return self
+                .mount_points
+                .iter()
+                .any(|allowed| allowed == mount_point)
+                && mount_point.is_dir();
+        }
+
+        // Check the volume labels
+        let matches_label = labels.iter().any(|label| {
+            self.volume_labels
+                .iter()
+                .any(|volume_label| volume_label.eq_ignore_ascii_case(label))
+        });
+
+        // Check the marker files
+        matches_label
+            || self
+                .marker_files
+                .iter()
+                .any(|marker_file| mount_point.join(marker_file).is_file())
+    }
+
+    /// Get the information about the board at the given location, if it is a board
+    ///
+    /// Boards without a readable boot_out.txt file are still detected, but only their
+    /// location is known.
+    pub fn detect(&self, mount_point: &Path, labels: &[&str]) -> Option<BoardInfo> {
+        if !self.is_board(mount_point, labels) {
+            return None;
+        }
+        let board = BoardInfo::from_mount(mount_point).unwrap_or_else(|_| BoardInfo {
+            mount_point: mount_point.to_path_buf(),
+            ..Default::default()
+        });
+        Some(board)
+    }
+}
+
+/// Board information parsing errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardInfoError {
+    /// The boot_out.txt file could not be read
+    MissingBootOut,
+    /// The boot_out.txt file is not formatted as expected
+    UnexpectedFormat,
+}
+
+/// Information about a connected CircuitPython board
 ///
-/// On success, returns the path of the board as a PathBuf.
-/// On error, return None.
-pub fn find_circuitpy() -> Option<PathBuf> {
-    for disk in Disks::new_with_refreshed_list().list() {
+/// This is parsed from the boot_out.txt file at the root of the board, which
+/// looks like the following:
+///
+/// ```text
+/// Adafruit CircuitPython 8.0.0-beta.6 on 2022-12-21; Adafruit Feather M4 Express with samd51j19
+/// Board ID:feather_m4_express
+/// UID:C4391B2B0D942955
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BoardInfo {
+    pub mount_point: PathBuf,
+    pub version: String,
+    pub build_date: String,
+    pub board_name: String,
+    pub mcu: String,
+    pub board_id: Option<String>,
+    pub uid: Option<String>,
+    #[serde(default)]
+    pub space: Option<DiskSpace>,
+}
+
+/// The total and available space of a disk, in bytes
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DiskSpace {
+    pub total: u64,
+    pub available: u64,
+}
+
+/// The fraction of the total space of a disk below which its available space is low
+pub const LOW_SPACE_FRACTION: f64 = 0.05;
+
+impl DiskSpace {
+    /// Checks whether the available space is below the low space threshold
+    pub fn is_low(&self) -> bool {
+        (self.available as f64) < (self.total as f64) * LOW_SPACE_FRACTION
+    }
+}
+
+impl BoardInfo {
+    /// Parse the contents of a boot_out.txt file for a board mounted at the given path
+    pub fn parse(mount_point: &Path, contents: &str) -> Result<Self, BoardInfoError> {
+        let mut lines = contents.lines();
+
+        // Parse the first line, which contains the version, build date, board name and MCU
+        let header = lines.next().ok_or(BoardInfoError::UnexpectedFormat)?;
+        let (_, header) = header
+            .split_once("CircuitPython ")
+            .ok_or(BoardInfoError::UnexpectedFormat)?;
+        let (version, header) = header
+            .split_once(" on ")
+            .ok_or(BoardInfoError::UnexpectedFormat)?;
+        let (build_date, header) = header
+            .split_once("; ")
+            .ok_or(BoardInfoError::UnexpectedFormat)?;
+        let (board_name, mcu) = header
+            .rsplit_once(" with ")
+            .ok_or(BoardInfoError::UnexpectedFormat)?;
+
+        // Create the board information, which the remaining lines can fill in
+        let mut info = BoardInfo {
+            mount_point: mount_point.to_path_buf(),
+            version: version.trim().to_owned(),
+            build_date: build_date.trim().to_owned(),
+            board_name: board_name.trim().to_owned(),
+            mcu: mcu.trim().to_owned(),
+            board_id: None,
+            uid: None,
+            space: None,
+        };
+
+        // Parse the board ID and UID from the remaining lines, if present
+        for line in lines {
+            if let Some(board_id) = line.strip_prefix("Board ID:") {
+                info.board_id = Some(board_id.trim().to_owned());
+            } else if let Some(uid) = line.strip_prefix("UID:") {
+                info.uid = Some(uid.trim().to_owned());
+            }
+        }
+
+        Ok(info)
+    }
+
+    /// Read the board information from the boot_out.txt file of the board mounted at
+    /// the given path
+    pub fn from_mount(mount_point: &Path) -> Result<Self, BoardInfoError> {
+        let contents = match fs::read_to_string(mount_point.join(BOOT_OUT_FILENAME)) {
+            Ok(contents) => contents,
+            Err(_) => return Err(BoardInfoError::MissingBootOut),
+        };
+        BoardInfo::parse(mount_point, &contents)
+    }
+
+    /// Get the major version of CircuitPython running on the board, if it can be parsed
+    pub fn major_version(&self) -> Option<u32> {
+        self.version.split('.').next()?.parse().ok()
+    }
+
+    /// Get the compiled Python file version the board can import, if known
+    pub fn mpy_version(&self) -> Option<u8> {
+        mpy_version_for(self.major_version()?)
+    }
+
+    /// Check whether the board matches the given selector, which can be the UID, the
+    /// board ID, or the mount point of the board
+    pub fn matches(&self, selector: &str) -> bool {
+        // Check the UID, ignoring case as it is a hexadecimal string
+        if let Some(uid) = &self.uid {
+            if uid.eq_ignore_ascii_case(selector) {
+                return true;
+            }
+        }
+
+        // Check the board ID
+        if self.board_id.as_deref() == Some(selector) {
+            return true;
+        }
+
+        // Check the mount point
+        match absolute(selector) {
+            Ok(selector_path) => selector_path == self.mount_point,
+            Err(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for BoardInfo {
+    /// Describe the board, including its board ID and UID when available so that
+    /// identical boards can be told apart
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.board_name.is_empty() {
+            write!(f, "Unknown board")?;
+        } else {
+            write!(f, "{}", self.board_name)?;
+        }
+        let identifiers: Vec<String> = [
+            self.board_id.clone(),
+            self.uid.as_ref().map(|uid| format!("UID {uid}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !identifiers.is_empty() {
+            write!(f, " ({})", identifiers.join(", "))?;
+        }
+        if !self.version.is_empty() {
+            write!(f, " running CircuitPython {}", self.version)?;
+        }
+        write!(f, " at {}", self.mount_point.display())
+    }
+}
+
+/// Board selection errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoardSelectionError {
+    /// No CircuitPython boards are connected
+    NoneConnected,
+    /// No connected board matches the given selector
+    NoMatch(String),
+    /// More than one connected board matches, so one must be selected
+    Ambiguous(Vec<BoardInfo>),
+    /// The board detection rules could not be loaded
+    InvalidDetectionRules(DetectionRulesError),
+}
+
+impl fmt::Display for BoardSelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardSelectionError::NoneConnected => {
+                write!(f, "Could not locate a connected CircuitPython board")
+            }
+            BoardSelectionError::NoMatch(selector) => {
+                write!(f, "No connected CircuitPython board matches '{selector}'")
+            }
+            BoardSelectionError::Ambiguous(boards) => {
+                write!(
+                    f,
+                    "Multiple CircuitPython boards are connected, use --board to select one:"
+                )?;
+                for board in boards {
+                    write!(f, "\n  {board}")?;
+                }
+                Ok(())
+            }
+            BoardSelectionError::InvalidDetectionRules(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+/// Find all connected CircuitPython boards, using the detection rules from the application
+/// directory
+pub fn find_all_circuitpy() -> Result<Vec<BoardInfo>, DetectionRulesError> {
+    let rules = DetectionRules::load()?;
+    let mut boards = Vec::new();
+
+    // Check the disks against the detection rules
+    let disks = Disks::new_with_refreshed_list();
+    for disk in disks.list() {
         let mount_point = disk.mount_point();
-        if mount_point.join("boot_out.txt").is_file() {
-            return Some(mount_point.to_path_buf());
+        let labels = disk_labels(disk);
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        if let Some(mut board) = rules.detect(mount_point, &labels) {
+            board.space = Some(DiskSpace {
+                total: disk.total_space(),
+                available: disk.available_space(),
+            });
+            boards.push(board);
+        }
+    }
+
+    // Check any allowed mount points that are plain directories rather than disks
+    for mount_point in &rules.mount_points {
+        if boards.iter().any(|board| &board.mount_point == mount_point) {
+            continue;
+        }
+        if let Some(mut board) = rules.detect(mount_point, &[]) {
+            board.space = disk_space(mount_point);
+            boards.push(board);
+        }
+    }
+
+    Ok(boards)
+}
+
+/// Get the volume labels of the given disk, which include the final component of its mount
+/// point, as that is how most systems name the mount points of removable disks
+fn disk_labels(disk: &Disk) -> Vec<String> {
+    let mut labels = vec![disk.name().to_string_lossy().to_string()];
+    if let Some(dirname) = disk.mount_point().file_name() {
+        labels.push(dirname.to_string_lossy().to_string());
+    }
+    labels
+}
+
+/// Find the disk containing the given path among the given disks, if any
+fn find_disk<'a>(disks: &'a Disks, path: &Path) -> Option<&'a Disk> {
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().components().count())
+}
+
+/// Get the space of the disk containing the given path, if it can be found
+pub fn disk_space(path: &Path) -> Option<DiskSpace> {
+    let disks = Disks::new_with_refreshed_list();
+    find_disk(&disks, path).map(|disk| DiskSpace {
+        total: disk.total_space(),
+        available: disk.available_space(),
+    })
+}
+
+/// Checks whether the given path cannot be written to, either because it is marked as
+/// read-only or because the disk containing it is mounted as read-only
+///
+/// If the path does not exist yet, its nearest existing ancestor is checked instead.
+pub fn is_read_only(path: &Path) -> bool {
+    let Some(existing) = path.ancestors().find(|ancestor| ancestor.exists()) else {
+        return false;
+    };
+    if fs::metadata(existing).is_ok_and(|metadata| metadata.permissions().readonly()) {
+        return true;
+    }
+    let disks = Disks::new_with_refreshed_list();
+    find_disk(&disks, existing).is_some_and(|disk| disk.is_read_only())
+}
+
+/// Format a number of bytes as a human readable size
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit_index = 0;
+    while size >= 1024.0 && unit_index < units.len() - 1 {
+        size /= 1024.0;
+        unit_index += 1;
+    }
+    if unit_index == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", units[unit_index])
+    }
+}
+
+/// Creates a header for the board table for use with tabled
+pub fn table_header() -> Vec<&'static str> {
+    vec![
+        "Mount Point",
+        "Board ID",
+        "UID",
+        "Version",
+        "Total Space",
+        "Free Space",
+        "Links",
+    ]
+}
+
+/// Creates a table of the given boards, including which of the given file monitors
+/// write to each of them
+pub fn as_table(boards: &[BoardInfo], monitors: &[FileMonitor]) -> Table {
+    // Create a tabled table to be built and add the header row
+    let mut table_builder = Builder::default();
+    table_builder.push_record(table_header());
+
+    // Add a row for each board
+    for board in boards {
+        // Get the numbers of the file monitors writing to the board
+        let link_numbers: Vec<String> = monitors
+            .iter()
+            .enumerate()
+            .filter(|(_, monitor)| monitor.write_directory.starts_with(&board.mount_point))
+            .map(|(index, _)| (index + 1).to_string())
+            .collect();
+
+        // Get the space of the board as strings, if known
+        let (total_space, free_space) = match board.space {
+            Some(space) => (format_size(space.total), format_size(space.available)),
+            None => (String::from("-"), String::from("-")),
+        };
+
+        table_builder.push_record(vec![
+            board.mount_point.to_string_lossy().to_string(),
+            board.board_id.clone().unwrap_or_else(|| String::from("-")),
+            board.uid.clone().unwrap_or_else(|| String::from("-")),
+            board.version.clone(),
+            total_space,
+            free_space,
+            link_numbers.join(", "),
+        ]);
+    }
+
+    // Return a built table
+    table_builder.build()
+}
+
+/// Select a single board from the given boards, optionally using a selector (see
+/// BoardInfo::matches())
+///
+/// If no selector is given, there must be exactly one board to select from.
+pub fn select_board(
+    boards: Vec<BoardInfo>,
+    selector: Option<&str>,
+) -> Result<BoardInfo, BoardSelectionError> {
+    // Return an error if there are no boards to select from
+    if boards.is_empty() {
+        return Err(BoardSelectionError::NoneConnected);
+    }
+
+    // Narrow down the boards using the selector, if provided
+    let mut candidates = match selector {
+        Some(selector) => {
+            let matched: Vec<BoardInfo> = boards
+                .into_iter()
+                .filter(|board| board.matches(selector))
+                .collect();
+            if matched.is_empty() {
+                return Err(BoardSelectionError::NoMatch(selector.to_owned()));
+            }
+            matched
         }
+        None => boards,
+    };
+
+    // Refuse to guess if more than one board remains
+    if candidates.len() > 1 {
+        return Err(BoardSelectionError::Ambiguous(candidates));
     }
-    None
+    Ok(candidates.remove(0))
+}
+
+/// Find the connected CircuitPython board, optionally using a selector (see
+/// BoardInfo::matches())
+///
+/// On success, returns the information about the board.
+/// On error, returns why a single board could not be selected.
+pub fn select_circuitpy(selector: Option<&str>) -> Result<BoardInfo, BoardSelectionError> {
+    let boards = find_all_circuitpy().map_err(BoardSelectionError::InvalidDetectionRules)?;
+    select_board(boards, selector)
+}
+
+/// Find the CircuitPython board that contains the given path, by checking the path and
+/// its ancestors against the detection rules from the application directory
+///
+/// Only the ancestors that are disk mount points are checked against the volume labels, so
+/// that ordinary directories named like boards are not detected as them.
+pub fn find_board_for_path(path: &Path) -> Result<Option<BoardInfo>, DetectionRulesError> {
+    let rules = DetectionRules::load()?;
+    let disks = Disks::new_with_refreshed_list();
+    Ok(path.ancestors().find_map(|ancestor| {
+        let labels = disks
+            .list()
+            .iter()
+            .find(|disk| disk.mount_point() == ancestor)
+            .map(disk_labels)
+            .unwrap_or_default();
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        rules.detect(ancestor, &labels)
+    }))
 }
 
 #[cfg(test)]
 mod test {
 
-    use std::fs;
-
     use super::*;
 
+    /// Get the filepath of the boot_out.txt test asset file
+    fn get_asset_filepath() -> PathBuf {
+        let current_filepath = PathBuf::from(file!());
+        let parent_filepath = current_filepath.parent().unwrap();
+        let grandparent_filepath = parent_filepath.parent().unwrap();
+        grandparent_filepath
+            .join("tests")
+            .join("assets")
+            .join(BOOT_OUT_FILENAME)
+    }
+
     /// Tests the ability to detect a connected CircuitPython board
     #[test]
     #[serial_test::serial]
     fn detection() {
         // Find the connected CircuitPython board
-        let mount_point: PathBuf = find_circuitpy().expect("Could not find CircuitPython board");
+        let mount_point: PathBuf = select_circuitpy(None)
+            .expect("Could not find CircuitPython board")
+            .mount_point;
 
         // Get the filename and filepath of the boot_out.txt file
-        let filename = "boot_out.txt";
-        let bootout_filepath = mount_point.as_path().join(&filename);
-
-        // Get the filepath of the boot_out.txt test asset file
-        let current_filepath = PathBuf::from(file!());
-        let parent_filepath = current_filepath.parent().unwrap();
-        let grandparent_filepath = parent_filepath.parent().unwrap();
-        let asset_filepath = grandparent_filepath
-            .join("tests")
-            .join("assets")
-            .join(&filename);
+        let bootout_filepath = mount_point.as_path().join(BOOT_OUT_FILENAME);
 
         // Get the contents of the boot_out.txt test asset file
-        let boutout_contents =
-            fs::read_to_string(&asset_filepath).expect("Could not read test asset of boot_out.txt");
+        let boutout_contents = fs::read_to_string(get_asset_filepath())
+            .expect("Could not read test asset of boot_out.txt");
 
         // Delete the boot_out.txt on the connected mount
         fs::remove_file(&bootout_filepath).expect("Could not delete file");
         assert!(!bootout_filepath.as_path().exists());
 
         // Assert that the board is no longer detected
-        assert!(find_circuitpy().is_none());
+        let error = select_circuitpy(None).expect_err("Found a CircuitPython board");
+        assert_eq!(error, BoardSelectionError::NoneConnected);
 
         // Return the boot_out.txt file to the connected mount
         fs::write(&bootout_filepath, &boutout_contents)
             .expect("Could not copy test bootout file after test");
         assert!(bootout_filepath.as_path().is_file());
     }
+
+    mod board_info {
+
+        use super::*;
+
+        use tempfile::TempDir;
+
+        /// Tests parsing the contents of the boot_out.txt test asset file
+        #[test]
+        fn parse() {
+            // Parse the boot_out.txt test asset file
+            let contents = fs::read_to_string(get_asset_filepath())
+                .expect("Could not read test asset of boot_out.txt");
+            let mount_point = PathBuf::from("/media/CIRCUITPY");
+            let info =
+                BoardInfo::parse(&mount_point, &contents).expect("Could not parse boot_out.txt");
+
+            // Check the parsed fields
+            assert_eq!(info.mount_point, mount_point);
+            assert_eq!(info.version, "8.0.0-beta.6");
+            assert_eq!(info.build_date, "2022-12-21");
+            assert_eq!(info.board_name, "Adafruit Feather M4 Express");
+            assert_eq!(info.mcu, "samd51j19");
+            assert_eq!(info.board_id.as_deref(), Some("feather_m4_express"));
+            assert_eq!(info.uid.as_deref(), Some("C4391B2B0D942955"));
+        }
+
+        /// Tests parsing a boot_out.txt file without the board ID and UID lines
+        #[test]
+        fn parse_header_only() {
+            let contents =
+                "Adafruit CircuitPython 9.2.1 on 2024-11-20; Raspberry Pi Pico with rp2040";
+            let info = BoardInfo::parse(Path::new("/media/CIRCUITPY"), contents)
+                .expect("Could not parse boot_out.txt");
+            assert_eq!(info.board_name, "Raspberry Pi Pico");
+            assert_eq!(info.mcu, "rp2040");
+            assert!(info.board_id.is_none());
+            assert!(info.uid.is_none());
+        }
+
+        /// Tests getting the CircuitPython major version and importable mpy version
+        #[test]
+        fn versions() {
+            let mut info = BoardInfo {
+                version: String::from("8.0.0-beta.6"),
+                ..Default::default()
+            };
+            assert_eq!(info.major_version(), Some(8));
+            assert_eq!(info.mpy_version(), Some(5));
+
+            info.version = String::from("9.2.1");
+            assert_eq!(info.major_version(), Some(9));
+            assert_eq!(info.mpy_version(), Some(6));
+
+            info.version = String::from("unknown");
+            assert_eq!(info.major_version(), None);
+            assert_eq!(info.mpy_version(), None);
+        }
+
+        /// Tests parsing a file that is not formatted like boot_out.txt
+        #[test]
+        fn parse_error() {
+            let error = BoardInfo::parse(Path::new("/media/CIRCUITPY"), "junkdata")
+                .expect_err("Successfully parsed a bad boot_out.txt");
+            assert_eq!(error, BoardInfoError::UnexpectedFormat);
+        }
+
+        /// Tests reading the board information from a directory without a boot_out.txt file
+        #[test]
+        fn from_mount_missing() {
+            let tempdir = TempDir::new().expect("Could not create temporary directory");
+            let error = BoardInfo::from_mount(tempdir.path())
+                .expect_err("Successfully read a nonexistent boot_out.txt");
+            assert_eq!(error, BoardInfoError::MissingBootOut);
+        }
+
+        /// Tests finding the board containing a path nested within it
+        #[test]
+        fn find_board_for_path() {
+            // Create a directory that stands in for a board
+            let tempdir = TempDir::new().expect("Could not create temporary directory");
+            fs::copy(get_asset_filepath(), tempdir.path().join(BOOT_OUT_FILENAME))
+                .expect("Could not copy boot_out.txt");
+
+            // Check that the board is found from a nested path
+            let nested = tempdir.path().join("lib").join("mylib");
+            let info = super::find_board_for_path(&nested)
+                .expect("Could not load the detection rules")
+                .expect("Could not find the board");
+            assert_eq!(info.mount_point, tempdir.path());
+            assert_eq!(info.uid.as_deref(), Some("C4391B2B0D942955"));
+        }
+
+        /// Tests that an ordinary directory named like a board is not found as one
+        #[test]
+        fn find_board_for_path_named_directory() {
+            let tempdir = TempDir::new().expect("Could not create temporary directory");
+            let nested = tempdir.path().join(DEFAULT_VOLUME_LABEL).join("lib");
+            fs::create_dir_all(&nested).expect("Could not create the directories");
+            let board =
+                super::find_board_for_path(&nested).expect("Could not load the detection rules");
+            assert!(board.is_none());
+        }
+    }
+
+    mod detection_rules {
+
+        use super::*;
+
+        use tempfile::TempDir;
+
+        /// Tests reading detection rules from a config file, where omitted rules use defaults
+        #[test]
+        fn from_file() {
+            let tempdir = TempDir::new().expect("Could not create temporary directory");
+            let config_file = tempdir.path().join("detection.json");
+            fs::write(&config_file, r#"{"volume_labels": ["MYBOARD"]}"#)
+                .expect("Could not write the config file");
+
+            let rules = DetectionRules::from_file(&config_file).expect("Could not read rules");
+            assert_eq!(rules.volume_labels, vec![String::from("MYBOARD")]);
+            assert_eq!(rules.marker_files, DetectionRules::default().marker_files);
+            assert!(rules.mount_points.is_empty());
+        }
+
+        /// Tests reading detection rules from missing and badly formatted config files
+        #[test]
+        fn from_file_error() {
+            let tempdir = TempDir::new().expect("Could not create temporary directory");
+            let config_file = tempdir.path().join("detection.json");
+            let error = DetectionRules::from_file(&config_file).unwrap_err();
+            assert_eq!(error, DetectionRulesError::Unreadable);
+
+            fs::write(&config_file, "junkdata").expect("Could not write the config file");
+            let error = DetectionRules::from_file(&config_file).unwrap_err();
+            assert_eq!(error, DetectionRulesError::UnexpectedFormat);
+        }
+
+        /// Tests detecting boards using volume labels
+        #[test]
+        fn labels() {
+            let rules = DetectionRules {
+                volume_labels: vec![String::from("MYBOARD")],
+                ..Default::default()
+            };
+            assert!(rules.is_board(Path::new("/media/user/disk"), &["myboard"]));
+            assert!(!rules.is_board(Path::new("/media/user/CIRCUITPY"), &["CIRCUITPY"]));
+
+            // Check that directories named like volume labels are not boards by themselves
+            assert!(!rules.is_board(Path::new("/home/user/MYBOARD"), &[]));
+        }
+
+        /// Tests detecting boards using marker files, including boards without boot_out.txt
+        #[test]
+        fn marker_files() {
+            let tempdir = TempDir::new().expect("Could not create temporary directory");
+            let rules = DetectionRules {
+                marker_files: vec![String::from("settings.toml")],
+                ..Default::default()
+            };
+            assert!(rules.detect(tempdir.path(), &[]).is_none());
+
+            fs::File::create(tempdir.path().join("settings.toml"))
+                .expect("Could not create marker file");
+            let board = rules
+                .detect(tempdir.path(), &[])
+                .expect("Could not detect the board");
+            assert_eq!(board.mount_point, tempdir.path());
+            assert!(board.uid.is_none());
+            assert_eq!(
+                board.to_string(),
+                format!("Unknown board at {}", tempdir.path().display())
+            );
+        }
+
+        /// Tests that only the allowed mount points are detected when given
+        #[test]
+        fn mount_points() {
+            let allowed = TempDir::new().expect("Could not create temporary directory");
+            let other = TempDir::new().expect("Could not create temporary directory");
+            fs::copy(get_asset_filepath(), other.path().join(BOOT_OUT_FILENAME))
+                .expect("Could not copy boot_out.txt");
+            let rules = DetectionRules {
+                mount_points: vec![allowed.path().to_path_buf()],
+                ..Default::default()
+            };
+            assert!(rules.is_board(allowed.path(), &[]));
+            assert!(!rules.is_board(other.path(), &[DEFAULT_VOLUME_LABEL]));
+        }
+
+        /// Tests finding a board for a path using the detection config file
+        #[test]
+        #[serial_test::serial]
+        fn find_board_for_path() {
+            // Save the current state of the application directory
+            let preexisted = crate::test_support::save_app_directory();
+
+            // Create a directory that stands in for a board, and allow only it as a board
+            let tempdir = TempDir::new().expect("Could not create temporary directory");
+            let rules = DetectionRules {
+                mount_points: vec![tempdir.path().to_path_buf()],
+                ..Default::default()
+            };
+            fs::write(
+                get_detection_config_file(),
+                serde_json::to_string(&rules).unwrap(),
+            )
+            .expect("Could not write the config file");
+
+            // Find the board from a nested path
+            let nested = tempdir.path().join("lib");
+            let board = super::find_board_for_path(&nested);
+
+            // Check that a badly formatted config file is reported rather than ignored
+            fs::write(get_detection_config_file(), "junkdata")
+                .expect("Could not write the config file");
+            let error = super::find_board_for_path(&nested).unwrap_err();
+
+            // Restore the previous application directory if it existed
+            crate::test_support::restore_app_directory(preexisted);
+
+            // Check that the stand-in board was found
+            let board = board
+                .expect("Could not load the detection rules")
+                .expect("Could not find the board");
+            assert_eq!(board.mount_point, tempdir.path());
+            assert_eq!(error, DetectionRulesError::UnexpectedFormat);
+        }
+    }
+
+    mod select_board {
+
+        use super::*;
+
+        /// Creates a list of boards for selection tests
+        fn get_boards() -> Vec<BoardInfo> {
+            let feather = BoardInfo {
+                mount_point: PathBuf::from("/media/CIRCUITPY"),
+                version: String::from("8.0.0"),
+                board_id: Some(String::from("feather_m4_express")),
+                uid: Some(String::from("C4391B2B0D942955")),
+                ..Default::default()
+            };
+            let pico = BoardInfo {
+                mount_point: PathBuf::from("/media/CIRCUITPY1"),
+                version: String::from("9.2.1"),
+                board_id: Some(String::from("raspberry_pi_pico")),
+                uid: Some(String::from("E6614103E7377B2F")),
+                ..Default::default()
+            };
+            vec![feather, pico]
+        }
+
+        /// Tests selecting the only connected board without a selector
+        #[test]
+        fn single() {
+            let mut boards = get_boards();
+            boards.truncate(1);
+            let board = select_board(boards.clone(), None).expect("Could not select the board");
+            assert_eq!(board, boards[0]);
+        }
+
+        /// Tests selecting a board by its UID, board ID and mount point
+        #[test]
+        fn selectors() {
+            let boards = get_boards();
+            for selector in ["e6614103e7377b2f", "raspberry_pi_pico", "/media/CIRCUITPY1"] {
+                let board = select_board(boards.clone(), Some(selector))
+                    .expect("Could not select the board");
+                assert_eq!(board, boards[1]);
+            }
+        }
+
+        /// Tests that selecting without a selector fails when multiple boards are connected
+        #[test]
+        fn ambiguous() {
+            let boards = get_boards();
+            let error = select_board(boards.clone(), None)
+                .expect_err("Selected a board when it should have been ambiguous");
+            assert_eq!(error, BoardSelectionError::Ambiguous(boards));
+        }
+
+        /// Tests that selecting fails when no board matches the selector
+        #[test]
+        fn no_match() {
+            let error = select_board(get_boards(), Some("doesnotexist"))
+                .expect_err("Selected a board that does not match");
+            assert_eq!(
+                error,
+                BoardSelectionError::NoMatch(String::from("doesnotexist"))
+            );
+        }
+
+        /// Tests that selecting fails when no boards are connected
+        #[test]
+        fn none_connected() {
+            let error = select_board(Vec::new(), None)
+                .expect_err("Selected a board when none are connected");
+            assert_eq!(error, BoardSelectionError::NoneConnected);
+        }
+    }
+
+    /// Tests formatting sizes as human readable strings
+    #[test]
+    fn format_size() {
+        assert_eq!(super::format_size(512), "512 B");
+        assert_eq!(super::format_size(2048), "2.0 KiB");
+        assert_eq!(super::format_size(1_500_000), "1.4 MiB");
+    }
+
+    /// Tests checking whether a disk is low on space
+    #[test]
+    fn is_low() {
+        let plenty = DiskSpace {
+            total: 2_000_000,
+            available: 1_000_000,
+        };
+        let low = DiskSpace {
+            total: 2_000_000,
+            available: 50_000,
+        };
+        assert!(!plenty.is_low());
+        assert!(low.is_low());
+    }
+
+    /// Tests getting the space of the disk containing a path
+    #[test]
+    fn disk_space() {
+        let tempdir = tempfile::TempDir::new().expect("Could not create temporary directory");
+        let space = super::disk_space(tempdir.path()).expect("Could not get the disk space");
+        assert!(space.total > 0);
+        assert!(space.available <= space.total);
+    }
+
+    /// Tests checking whether paths are read-only
+    #[test]
+    fn is_read_only() {
+        let tempdir = tempfile::TempDir::new().expect("Could not create temporary directory");
+        let missing = tempdir.path().join("does/not/exist");
+        assert!(!super::is_read_only(tempdir.path()));
+        assert!(!super::is_read_only(&missing));
+
+        // Mark the directory as read-only, which also applies to paths that do not exist yet
+        let mut permissions = fs::metadata(tempdir.path())
+            .expect("Could not get the directory metadata")
+            .permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(tempdir.path(), permissions.clone())
+            .expect("Could not mark the directory as read-only");
+        assert!(super::is_read_only(tempdir.path()));
+        assert!(super::is_read_only(&missing));
+
+        // Restore the permissions so the directory can be cleaned up
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(tempdir.path(), permissions)
+            .expect("Could not mark the directory as writable");
+    }
+
+    /// Tests creating a table of boards along with the file monitors writing to them
+    #[test]
+    fn as_table() {
+        // Create the board to show in the table
+        let board = BoardInfo {
+            mount_point: PathBuf::from("/media/CIRCUITPY"),
+            version: String::from("8.0.0"),
+            board_id: Some(String::from("feather_m4_express")),
+            uid: Some(String::from("C4391B2B0D942955")),
+            space: Some(DiskSpace {
+                total: 2048,
+                available: 1024,
+            }),
+            ..Default::default()
+        };
+
+        // Create file monitors, where only the first and third write to the board
+        let base_directory = PathBuf::from("/circpush");
+        let monitors = vec![
+            FileMonitor::new(&["test*"], &board.mount_point, &base_directory),
+            FileMonitor::new(&["test*"], Path::new("/media/OTHER"), &base_directory),
+            FileMonitor::new(&["test*"], &board.mount_point.join("lib"), &base_directory),
+        ];
+
+        // Create the table and parse its contents
+        let table = super::as_table(&[board], &monitors).to_string();
+        let contents = crate::test_support::parse_contents(&table, false);
+
+        // Check the contents of the table
+        let expected = vec![
+            super::table_header()
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<String>>(),
+            [
+                "/media/CIRCUITPY",
+                "feather_m4_express",
+                "C4391B2B0D942955",
+                "8.0.0",
+                "2.0 KiB",
+                "1.0 KiB",
+                "1, 3",
+            ]
+            .iter()
+            .map(|e| e.to_string())
+            .collect(),
+        ];
+        assert_eq!(contents, expected);
+    }
 }
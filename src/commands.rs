@@ -1,10 +1,10 @@
 // SPDX-FileCopyrightText: 2025 Alec Delaney
 // SPDX-License-Identifier: MIT
 
-use std::path::PathBuf;
-
 use serde::{Deserialize, Serialize};
 
+use crate::monitor::FileMonitor;
+
 /// The response sent by the server to the client confirming that it will stop
 pub const STOP_RESPONSE: &str = "@stopping";
 
@@ -16,21 +16,11 @@ pub const STOP_RESPONSE: &str = "@stopping";
 pub enum Request {
     Ping,
     Shutdown,
-    StartLink {
-        read_pattern: String,
-        write_directory: PathBuf,
-        base_directory: PathBuf,
-    },
-    StopLink {
-        number: usize,
-    },
-    ViewLink {
-        number: usize,
-    },
+    StartLink { monitor: Box<FileMonitor> },
+    StopLink { number: usize },
+    ViewLink { number: usize },
     ViewWorkspaceName,
-    SetWorkspaceName {
-        name: String,
-    },
+    SetWorkspaceName { name: String },
 }
 
 /// Various types of responses from the TCP server to the client
@@ -12,6 +12,15 @@ pub const WORKSPACE_DIRNAME: &str = "workspaces";
 /// The port directory name
 pub const PORT_DIRNAME: &str = "port";
 
+/// The backup directory name
+pub const BACKUP_DIRNAME: &str = "backups";
+
+/// The board detection config filename
+pub const DETECTION_FILENAME: &str = "detection.json";
+
+/// The web workflow credential store filename
+pub const CREDENTIALS_FILENAME: &str = "credentials.json";
+
 /// Get the application directory path
 pub fn get_app_dir() -> PathBuf {
     let config_dir = dirs::config_dir().expect("Could not locate config directory");
@@ -46,6 +55,21 @@ pub fn ensure_workspace_dir() {
     fs::create_dir_all(dir).expect("Could not create workspace directory");
 }
 
+/// Get the backup directory path
+pub fn get_backup_dir() -> PathBuf {
+    get_app_dir().join(BACKUP_DIRNAME)
+}
+
+/// Get the board detection config filepath
+pub fn get_detection_config_file() -> PathBuf {
+    get_app_dir().join(DETECTION_FILENAME)
+}
+
+/// Get the web workflow credential store filepath
+pub fn get_credentials_file() -> PathBuf {
+    get_app_dir().join(CREDENTIALS_FILENAME)
+}
+
 #[cfg(all(feature = "test-support", test))]
 mod test {
     use std::path::PathBuf;
@@ -83,6 +107,22 @@ mod test {
         assert!(app_dir.ends_with(endpath))
     }
 
+    /// Tests that the board detection config filepath is approrpiate
+    #[test]
+    fn get_detection_config_file() {
+        let config_file = super::get_detection_config_file();
+        let endpath = PathBuf::from(env!("CARGO_PKG_NAME")).join(DETECTION_FILENAME);
+        assert!(config_file.ends_with(endpath))
+    }
+
+    /// Tests that the web workflow credential store filepath is appropriate
+    #[test]
+    fn get_credentials_file() {
+        let credentials_file = super::get_credentials_file();
+        let endpath = PathBuf::from(env!("CARGO_PKG_NAME")).join(CREDENTIALS_FILENAME);
+        assert!(credentials_file.ends_with(endpath))
+    }
+
     /// Tests that ensuring the application workspace directory works
     #[test]
     #[serial_test::serial]
@@ -0,0 +1,285 @@
+// SPDX-FileCopyrightText: 2025 Alec Delaney
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{absolute, Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde::{Deserialize, Serialize};
+
+/// The number of bytes read from the start of a file to check whether it is a text file
+const TEXT_CHECK_LENGTH: u64 = 8000;
+
+/// The names of the ignore files read from each directory, in increasing order of priority
+pub const IGNORE_FILENAMES: [&str; 2] = [".gitignore", ".circpushignore"];
+
+/// Filters for the source files matched by a file monitor, based on their size and type
+///
+/// Empty filters allow all files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct FileFilters {
+    /// The maximum size of the files, in bytes
+    pub max_size: Option<u64>,
+    /// The minimum size of the files, in bytes
+    pub min_size: Option<u64>,
+    /// Whether to only allow text files
+    pub text_only: bool,
+    /// The file extensions allowed, without the leading dot, or all if empty
+    pub extensions: Vec<String>,
+}
+
+impl FileFilters {
+    /// Checks whether the given source file passes the filters
+    pub fn allows(&self, path: &Path) -> bool {
+        // Check the file extension
+        if !self.extensions.is_empty() {
+            let Some(extension) = path.extension().and_then(|extension| extension.to_str()) else {
+                return false;
+            };
+            if !self
+                .extensions
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(extension))
+            {
+                return false;
+            }
+        }
+
+        // Check the file size
+        if self.max_size.is_some() || self.min_size.is_some() {
+            let Ok(metadata) = fs::metadata(path) else {
+                return false;
+            };
+            let size = metadata.len();
+            if self.max_size.is_some_and(|max_size| size > max_size)
+                || self.min_size.is_some_and(|min_size| size < min_size)
+            {
+                return false;
+            }
+        }
+
+        // Check the file contents
+        !self.text_only || is_text_file(path)
+    }
+}
+
+/// Checks whether the given file is a text file, based on whether the start of it contains
+/// any null bytes
+pub fn is_text_file(path: &Path) -> bool {
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    let mut start = Vec::new();
+    if file
+        .take(TEXT_CHECK_LENGTH)
+        .read_to_end(&mut start)
+        .is_err()
+    {
+        return false;
+    }
+    !start.contains(&0)
+}
+
+/// The rules from the ignore files that apply to source files, read as they are needed
+///
+/// Each directory from the repository root (the directory containing `.git`) down to a file can
+/// have `.gitignore` and `.circpushignore` files, with deeper ignore files taking priority, and
+/// `.circpushignore` taking priority over `.gitignore` in the same directory.  The repository's
+/// `.git/info/exclude` file applies with the lowest priority.  Without a repository, ignore files
+/// are read from all of the ancestors of the base directory.
+#[derive(Debug, Clone)]
+pub struct IgnoreRules {
+    root: Option<PathBuf>,
+    exclude: Option<Gitignore>,
+    directories: HashMap<PathBuf, Vec<Gitignore>>,
+}
+
+impl IgnoreRules {
+    /// Finds the ignore rules for the source files of the given base directory
+    pub fn load(base_directory: &Path) -> Self {
+        let base_directory = absolute(base_directory).unwrap_or(base_directory.to_path_buf());
+        let root = base_directory
+            .ancestors()
+            .find(|ancestor| ancestor.join(".git").exists())
+            .map(Path::to_path_buf);
+        let exclude = root.as_ref().and_then(|root| {
+            let exclude_path = root.join(".git/info/exclude");
+            exclude_path.is_file().then(|| {
+                // The exclude file's patterns are relative to the repository root
+                let mut builder = GitignoreBuilder::new(root);
+                builder.add(&exclude_path);
+                builder.build().unwrap_or_else(|_| Gitignore::empty())
+            })
+        });
+        IgnoreRules {
+            root,
+            exclude,
+            directories: HashMap::new(),
+        }
+    }
+
+    /// Checks whether the given file is ignored by the ignore rules, or is within a `.git`
+    /// directory
+    pub fn is_ignored(&mut self, path: &Path) -> bool {
+        let path = absolute(path).unwrap_or(path.to_path_buf());
+        if path
+            .components()
+            .any(|component| component.as_os_str() == ".git")
+        {
+            return true;
+        }
+
+        // Check the ignore files from the directory of the file upward
+        for directory in path.ancestors().skip(1) {
+            let ignore_files = self
+                .directories
+                .entry(directory.to_path_buf())
+                .or_insert_with(|| read_ignore_files(directory));
+            for ignore_file in ignore_files.iter() {
+                match ignore_file.matched_path_or_any_parents(&path, false) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+            if self.root.as_deref() == Some(directory) {
+                break;
+            }
+        }
+
+        // Check the repository's exclude file
+        match (&self.root, &self.exclude) {
+            (Some(root), Some(exclude)) if path.starts_with(root) => exclude
+                .matched_path_or_any_parents(&path, false)
+                .is_ignore(),
+            _ => false,
+        }
+    }
+}
+
+/// Reads the ignore files in the given directory, in decreasing order of priority
+fn read_ignore_files(directory: &Path) -> Vec<Gitignore> {
+    IGNORE_FILENAMES
+        .iter()
+        .rev()
+        .map(|filename| directory.join(filename))
+        .filter(|ignore_path| ignore_path.is_file())
+        .map(|ignore_path| Gitignore::new(ignore_path).0)
+        .collect()
+}
+
+/// Parses a file size in bytes, with an optional K, M or G suffix for kibibytes, mebibytes or
+/// gibibytes (e.g., "512K")
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let (number, multiplier) = match size.char_indices().last() {
+        Some((index, 'K' | 'k')) => (&size[..index], 1024),
+        Some((index, 'M' | 'm')) => (&size[..index], 1024 * 1024),
+        Some((index, 'G' | 'g')) => (&size[..index], 1024 * 1024 * 1024),
+        _ => (size, 1),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid file size '{size}'"))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use tempfile::TempDir;
+
+    /// Tests filtering files by extension, size and type
+    #[test]
+    fn allows() {
+        let directory = TempDir::new().expect("Could not create temporary directory");
+        let text_path = directory.path().join("code.py");
+        let binary_path = directory.path().join("image.BMP");
+        fs::write(&text_path, "print('hello')\n").expect("Could not write file");
+        fs::write(&binary_path, [b'B', b'M', 0, 0, 0, 0]).expect("Could not write file");
+
+        // Check that empty filters allow all files
+        let mut filters = FileFilters::default();
+        assert!(filters.allows(&text_path));
+        assert!(filters.allows(&binary_path));
+
+        // Check filtering by extension, ignoring case
+        filters.extensions = vec![String::from("mpy"), String::from("bmp")];
+        assert!(!filters.allows(&text_path));
+        assert!(filters.allows(&binary_path));
+        filters.extensions.clear();
+
+        // Check filtering by size
+        filters.max_size = Some(10);
+        assert!(!filters.allows(&text_path));
+        assert!(filters.allows(&binary_path));
+        filters.max_size = None;
+        filters.min_size = Some(10);
+        assert!(filters.allows(&text_path));
+        assert!(!filters.allows(&binary_path));
+        filters.min_size = None;
+
+        // Check filtering by type
+        filters.text_only = true;
+        assert!(filters.allows(&text_path));
+        assert!(!filters.allows(&binary_path));
+    }
+
+    /// Tests ignoring files using ignore files in the base directory, its subdirectories and
+    /// its ancestors, as well as the repository's exclude file
+    #[test]
+    fn ignore_rules() {
+        // Create a repository with a project inside of it
+        let directory = TempDir::new().expect("Could not create temporary directory");
+        let root = directory.path();
+        let base_directory = root.join("project");
+        fs::create_dir_all(root.join(".git/info")).expect("Could not create directory");
+        fs::create_dir_all(base_directory.join("lib")).expect("Could not create directory");
+        fs::write(root.join(".git/info/exclude"), "*.log\n").expect("Could not write file");
+        fs::write(root.join(".gitignore"), "*.bak\nbuild/\n").expect("Could not write file");
+        fs::write(base_directory.join(".circpushignore"), "docs/\n!keep.bak\n")
+            .expect("Could not write file");
+        fs::write(base_directory.join("lib/.gitignore"), "generated.py\n")
+            .expect("Could not write file");
+
+        // Check which files are ignored
+        let mut rules = IgnoreRules::load(&base_directory);
+        for (filename, is_ignored) in [
+            ("code.py", false),
+            ("debug.log", true),
+            ("code.bak", true),
+            ("keep.bak", false),
+            ("build/code.py", true),
+            ("docs/index.md", true),
+            ("lib/generated.py", true),
+            ("lib/module.py", false),
+            (".git/config", true),
+        ] {
+            let path = base_directory.join(filename);
+            assert_eq!(rules.is_ignored(&path), is_ignored, "{filename}");
+        }
+
+        // Check that the repository's ignore files do not apply outside of it
+        let outside = TempDir::new().expect("Could not create temporary directory");
+        let mut rules = IgnoreRules::load(outside.path());
+        assert!(!rules.is_ignored(&outside.path().join("debug.log")));
+    }
+
+    /// Tests parsing file sizes with and without suffixes
+    #[test]
+    fn parse_size() {
+        assert_eq!(super::parse_size("100"), Ok(100));
+        assert_eq!(super::parse_size("512K"), Ok(512 * 1024));
+        assert_eq!(super::parse_size("2m"), Ok(2 * 1024 * 1024));
+        assert_eq!(super::parse_size("1G"), Ok(1024 * 1024 * 1024));
+        assert!(super::parse_size("big").is_err());
+        assert!(super::parse_size("K").is_err());
+    }
+}
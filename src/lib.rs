@@ -1,25 +1,40 @@
 // SPDX-FileCopyrightText: 2025 Alec Delaney
 // SPDX-License-Identifier: MIT
 
+mod backup;
 mod board;
 mod commands;
 mod filetree;
+mod filter;
 mod link;
+mod mapping;
 mod monitor;
+mod mpy;
+mod serial;
 mod tcp;
+mod transform;
+mod watch;
+mod web;
 mod workspace;
 
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::{env, path::absolute};
 
 use filetree::{ensure_port_dir, ensure_workspace_dir};
 use pyo3::prelude::*;
 use std::process::exit;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
-use crate::board::find_circuitpy;
+use crate::board::select_circuitpy;
 use crate::filetree::ensure_app_dir;
+use crate::filter::{parse_size, FileFilters};
+use crate::link::ChangeDetection;
+use crate::mapping::{shared_destinations, PathMapping};
+use crate::monitor::{DeletionPolicy, FileMonitor};
+use crate::transform::Transform;
+use crate::watch::WatchBackend;
+use crate::web::WebWorkflow;
 
 /// Python module created using PyO3 (circpush)
 #[pymodule]
@@ -64,6 +79,7 @@ struct Cli {
 
 /// Main CLI command options
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Command {
     /// Server-specific commands (e.g., start and stop)
     #[command(subcommand)]
@@ -74,14 +90,88 @@ enum Command {
         #[arg(short, long)]
         port: Option<u16>,
     },
-    /// Start a file monitor for a given filename or glob pattern
+    /// Start a file monitor for the given filenames or glob patterns
     #[command(name = "start")]
     LinkStart {
-        /// The filename or glob pattern to monitor
-        read_pattern: String,
+        /// The filenames or glob patterns to monitor
+        #[arg(required = true)]
+        read_patterns: Vec<String>,
         /// Use a given path as the write location instead of the connected CircuitPython board
         #[arg(short, long, value_name = "PATH")]
         path: Option<PathBuf>,
+        /// Select the connected CircuitPython board to write to when multiple are connected
+        #[arg(
+            short,
+            long,
+            value_name = "UID|BOARD_ID|MOUNT",
+            conflicts_with = "path"
+        )]
+        board: Option<String>,
+        /// Write to the given directory on the board (e.g., "lib/mylib") instead of its root,
+        /// keeping it when the file monitor is used with other boards
+        #[arg(
+            long,
+            value_name = "DIRECTORY",
+            value_parser = parse_destination,
+            conflicts_with = "path"
+        )]
+        dest: Option<PathBuf>,
+        /// Soft reboot the board over the given serial port after files are written
+        #[arg(short, long, value_name = "PORT")]
+        serial_port: Option<PathBuf>,
+        /// Write to a board over the web workflow at the given URL instead of a connected board
+        #[arg(short, long, value_name = "URL", conflicts_with_all = ["path", "board"])]
+        web: Option<String>,
+        /// The web workflow password for the board, which is kept in the application directory
+        /// rather than in saved workspaces
+        #[arg(long, requires = "web")]
+        password: Option<String>,
+        /// How to detect changed files that need to be written
+        #[arg(short, long, value_enum, default_value_t = ChangeDetection::Mtime)]
+        compare: ChangeDetection,
+        /// Transform the contents of files before writing them, in the order given
+        #[arg(short, long, value_enum, value_name = "TRANSFORM")]
+        transform: Vec<TransformOption>,
+        /// Compile Python files using the given command (e.g., "mpy-cross {input} -o {output}"),
+        /// writing them as .mpy files after any other transforms
+        #[arg(long, value_name = "COMMAND")]
+        compile: Option<String>,
+        /// Follow symlinked files and directories, including the base and write directories
+        #[arg(long)]
+        follow_symlinks: bool,
+        /// What to do with written files once their source files are removed
+        #[arg(long, value_enum, default_value_t = DeletionPolicy::Mirror)]
+        deletion: DeletionPolicy,
+        /// Only write files up to the given size, in bytes or with a K, M or G suffix
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_size: Option<u64>,
+        /// Only write files of at least the given size, in bytes or with a K, M or G suffix
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        min_size: Option<u64>,
+        /// Only write text files
+        #[arg(long)]
+        text_only: bool,
+        /// Only write files with the given extensions (e.g., "py,mpy,bmp")
+        #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',')]
+        ext: Vec<String>,
+        /// Exclude files matching the given glob pattern, or within directories matching it,
+        /// which matches names at any depth if it has no "/" (e.g., "__pycache__")
+        #[arg(short = 'x', long, value_name = "PATTERN", value_parser = parse_exclude_pattern)]
+        exclude: Vec<String>,
+        /// Skip files ignored by the .gitignore, .git/info/exclude and .circpushignore files
+        /// of the project
+        #[arg(long)]
+        respect_ignore: bool,
+        /// Write the given source file to the given destination instead (e.g.,
+        /// "apps/thermostat/main.py=code.py")
+        #[arg(long, value_name = "SOURCE=DEST", value_parser = parse_mapping)]
+        map: Vec<PathMapping>,
+        /// Write source files whose paths match the given regular expression to the given
+        /// replacement instead, which can refer to capture groups (e.g., "src/(.*)=lib/$1"),
+        /// after any --map mappings; files added later that are written to the same destination
+        /// as others are only reported while running
+        #[arg(long, value_name = "REGEX=DEST", value_parser = parse_rewrite)]
+        rewrite: Vec<PathMapping>,
     },
     /// Stop a file monitor
     #[command(name = "stop")]
@@ -103,6 +193,17 @@ enum Command {
     /// View all currently monitored files
     #[command(name = "ledger")]
     LinkLedger,
+    /// List the connected CircuitPython boards
+    Boards,
+    /// Restore a file on a connected CircuitPython board from the backups saved before it was
+    /// overwritten
+    Restore {
+        /// The file on the board to restore
+        path: PathBuf,
+        /// The version of the backup to restore, instead of the latest
+        #[arg(long, value_name = "N")]
+        version: Option<u32>,
+    },
     /// Workspace-specific commands (e.g., save and load)
     #[command(subcommand)]
     Workspace(WorkspaceCommand),
@@ -116,12 +217,18 @@ enum ServerCommand {
         /// The TCP port to use for the server
         #[arg(short, long)]
         port: Option<u16>,
+        /// How to watch for changes to the monitored files
+        #[arg(long, value_enum, default_value_t = WatchBackend::Notify)]
+        watcher: WatchBackend,
     },
     /// Start the server in a new process
     Start {
         /// The TCP port to use for the server
         #[arg(short, long)]
         port: Option<u16>,
+        /// How to watch for changes to the monitored files
+        #[arg(long, value_enum, default_value_t = WatchBackend::Notify)]
+        watcher: WatchBackend,
     },
     /// Stop the server
     Stop,
@@ -145,6 +252,9 @@ enum WorkspaceCommand {
     Load {
         /// The name of the workspace
         name: String,
+        /// Write to the given CircuitPython board instead of the one the workspace was saved with
+        #[arg(short, long, value_name = "UID|BOARD_ID|MOUNT")]
+        board: Option<String>,
     },
     /// List all saved workspaces
     List,
@@ -172,6 +282,70 @@ enum WorkspaceCommand {
     },
 }
 
+/// The built-in transforms that can be selected from the CLI
+#[derive(Clone, Copy, ValueEnum)]
+enum TransformOption {
+    /// Convert CRLF line endings to LF in text files
+    NormalizeLineEndings,
+    /// Strip comments and docstrings from Python files
+    StripComments,
+}
+
+impl From<TransformOption> for Transform {
+    fn from(option: TransformOption) -> Self {
+        match option {
+            TransformOption::NormalizeLineEndings => Transform::NormalizeLineEndings,
+            TransformOption::StripComments => Transform::StripComments,
+        }
+    }
+}
+
+/// Parses an exclude pattern, checking that it is a valid glob pattern
+fn parse_exclude_pattern(pattern: &str) -> Result<String, String> {
+    match glob::Pattern::new(pattern) {
+        Ok(_) => Ok(pattern.trim_end_matches('/').to_string()),
+        Err(error) => Err(format!("invalid glob pattern: {error}")),
+    }
+}
+
+/// Parses a destination directory on a board, relative to the root of the board
+fn parse_destination(destination: &str) -> Result<PathBuf, String> {
+    let mut path = PathBuf::new();
+    for component in Path::new(destination).components() {
+        match component {
+            Component::RootDir | Component::CurDir => {}
+            Component::Normal(name) => path.push(name),
+            Component::ParentDir | Component::Prefix(_) => {
+                return Err(String::from("the directory must be within the board"))
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// Parses a mapping of a source file to a destination, given as "SOURCE=DEST"
+fn parse_mapping(mapping: &str) -> Result<PathMapping, String> {
+    PathMapping::parse_file(mapping).map_err(|error| error.to_string())
+}
+
+/// Parses a rewrite of source paths matching a regular expression, given as "REGEX=DEST"
+fn parse_rewrite(rewrite: &str) -> Result<PathMapping, String> {
+    PathMapping::parse_rewrite(rewrite).map_err(|error| error.to_string())
+}
+
+/// Gets the error for different source files mapped to the same destination
+fn shared_destination_error(destination: &Path, sources: &[&Path]) -> String {
+    let sources: Vec<String> = sources
+        .iter()
+        .map(|source| format!("'{}'", source.display()))
+        .collect();
+    format!(
+        "{} cannot all be mapped to '{}'",
+        sources.join(", "),
+        destination.display()
+    )
+}
+
 /// Main entry for the CLI
 pub fn entry(cli_args: &[String]) -> Result<String, String> {
     // Ensure all necessary folders are created
@@ -186,52 +360,133 @@ pub fn entry(cli_args: &[String]) -> Result<String, String> {
         Command::Workspace(workspace_command) => workspace_subentry(workspace_command),
         Command::Ping { port } => crate::tcp::client::ping(port),
         Command::LinkStart {
-            read_pattern,
-            mut path,
+            read_patterns,
+            path,
+            board,
+            dest,
+            serial_port,
+            web,
+            password,
+            compare,
+            transform,
+            compile,
+            follow_symlinks,
+            deletion,
+            max_size,
+            min_size,
+            text_only,
+            ext,
+            exclude,
+            respect_ignore,
+            map,
+            rewrite,
         } => {
-            // If no path is provided, attempt to find the connected CircuitPython board
-            if path.is_none() {
-                path = find_circuitpy();
+            // Refuse mappings of different source files to the same destination
+            if let Some((destination, sources)) = shared_destinations(&map).first() {
+                return Err(shared_destination_error(destination, sources));
             }
 
-            // If the path is still not found, return as an error
-            if path.is_none() {
-                return Err(String::from(
-                    "Could not locate a connected CircuitPython board",
-                ));
-            }
+            let base_directory = env::current_dir().expect("Could not get the current directory");
+            let mut monitor = match web {
+                // If a web workflow URL is provided, write to the root of that board
+                Some(url) => match WebWorkflow::new(&url, &password.unwrap_or_default()) {
+                    Ok(web_workflow) => FileMonitor::new_web(
+                        &read_patterns,
+                        web_workflow,
+                        &Path::new("/").join(dest.as_deref().unwrap_or(Path::new(""))),
+                        &base_directory,
+                    ),
+                    Err(error) => return Err(format!("Could not use '{url}': {error}")),
+                },
+                None => {
+                    // If no path is provided, attempt to find the (selected) connected
+                    // CircuitPython board, writing to the destination on it if one is given
+                    let path = match path {
+                        Some(path) => path,
+                        None => match select_circuitpy(board.as_deref()) {
+                            Ok(board) => match &dest {
+                                Some(dest) => board.mount_point.join(dest),
+                                None => board.mount_point,
+                            },
+                            Err(error) => return Err(error.to_string()),
+                        },
+                    };
+                    FileMonitor::new(
+                        &read_patterns,
+                        &absolute(path).expect("Could not get the current directory"),
+                        &base_directory,
+                    )
+                }
+            };
 
             // Start the link with the provided information via request to server
-            crate::tcp::client::start_monitor(
-                read_pattern,
-                absolute(path.unwrap()).expect("Could not get the current directory"),
-                env::current_dir().expect("Could not get the current directory"),
-            )
+            monitor.destination = dest;
+            monitor.serial_port = serial_port;
+            monitor.change_detection = compare;
+            monitor.transforms = transform.into_iter().map(Transform::from).collect();
+            if let Some(command) = compile {
+                monitor.transforms.push(Transform::compile_python(&command));
+            }
+            monitor.follow_symlinks = follow_symlinks;
+            monitor.deletion_policy = deletion;
+            monitor.filters = FileFilters {
+                max_size,
+                min_size,
+                text_only,
+                extensions: ext
+                    .iter()
+                    .map(|extension| extension.trim().trim_start_matches('.').to_string())
+                    .filter(|extension| !extension.is_empty())
+                    .collect(),
+            };
+            monitor.exclude_patterns = exclude;
+            monitor.respect_ignore_files = respect_ignore;
+            let has_rewrites = !rewrite.is_empty();
+            monitor.mappings = map.into_iter().chain(rewrite).collect();
+
+            // Refuse rewrites of different source files currently matched to the same destination
+            if has_rewrites {
+                if let Ok(shared) = monitor.find_shared_destinations() {
+                    if let Some((destination, sources)) = shared.first() {
+                        let destination = destination
+                            .strip_prefix(&monitor.write_directory)
+                            .unwrap_or(destination);
+                        let sources: Vec<&Path> = sources
+                            .iter()
+                            .map(|source| source.strip_prefix(&base_directory).unwrap_or(source))
+                            .collect();
+                        return Err(shared_destination_error(destination, &sources));
+                    }
+                }
+            }
+            crate::tcp::client::start_monitor(monitor)
         }
         Command::LinkStop { number } => crate::tcp::client::stop_monitor(number),
         Command::LinkView { number, absolute } => {
             crate::tcp::client::view_monitor(number, absolute)
         }
         Command::LinkLedger => Err(String::from("WIP")),
+        Command::Boards => crate::tcp::client::view_boards(),
+        Command::Restore { path, version } => crate::backup::restore(&path, version),
     }
 }
 
 /// Server command subentry, for performing the appropriate command
 fn server_subentry(server_command: ServerCommand) -> Result<String, String> {
     match server_command {
-        ServerCommand::Run { port } => {
+        ServerCommand::Run { port, watcher } => {
             if crate::tcp::server::is_server_running() {
                 return Err(String::from("Server already running"));
             }
             let port = port.unwrap_or_default();
-            Ok(crate::tcp::server::run_server(port)?)
+            Ok(crate::tcp::server::run_server(port, watcher)?)
         }
-        ServerCommand::Start { port } => {
+        ServerCommand::Start { port, watcher } => {
             if crate::tcp::server::is_server_running() {
                 return Err(String::from("Server already running"));
             }
             let port = port.unwrap_or_default();
-            crate::tcp::server::start_server(port)
+            crate::tcp::server::start_server(port, watcher)
         }
         ServerCommand::Stop => crate::tcp::client::stop_server(),
     }
@@ -248,7 +503,9 @@ fn workspace_subentry(workspace_command: WorkspaceCommand) -> Result<String, Str
             let desc = description.unwrap_or_default();
             crate::tcp::client::save_workspace(&name, &desc, force)
         }
-        WorkspaceCommand::Load { name } => crate::tcp::client::load_workspace(&name),
+        WorkspaceCommand::Load { name, board } => {
+            crate::tcp::client::load_workspace(&name, board.as_deref())
+        }
         WorkspaceCommand::List => crate::workspace::list_workspaces(),
         WorkspaceCommand::View { name, absolute } => {
             crate::workspace::view_workspace(&name, absolute)
@@ -271,7 +528,10 @@ pub mod test_support {
 
     use std::{
         fs,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
         path::{Path, PathBuf},
+        sync::{Arc, Mutex},
     };
 
     /// The test configuration directory name
@@ -280,7 +540,7 @@ pub mod test_support {
     /// Test helper function for starting the server
     pub fn start_server() {
         thread::spawn(|| {
-            let _resp = server::run_server(0);
+            let _resp = server::run_server(0, WatchBackend::default());
         });
         while tcp::client::ping(None).is_err() {}
     }
@@ -493,4 +753,90 @@ pub mod test_support {
         // Return the list of components for the table
         components
     }
+
+    /// A request received by the web workflow stand-in server
+    #[derive(Debug, Clone)]
+    pub struct WebWorkflowRequest {
+        pub method: String,
+        pub route: String,
+        pub headers: String,
+        pub body: Vec<u8>,
+    }
+
+    /// A local HTTP server standing in for a board using the web workflow, which responds
+    /// with the status code chosen for each request and records the requests it receives
+    pub struct WebWorkflowStandIn {
+        port: u16,
+        requests: Arc<Mutex<Vec<WebWorkflowRequest>>>,
+    }
+
+    impl WebWorkflowStandIn {
+        /// Test helper function for starting the stand-in server in a separate thread
+        pub fn start<F>(respond: F) -> Self
+        where
+            F: Fn(&WebWorkflowRequest) -> u16 + Send + 'static,
+        {
+            // Bind to a randomly assigned port
+            let listener = TcpListener::bind("127.0.0.1:0").expect("Could not bind stand-in");
+            let port = listener.local_addr().expect("Could not get port").port();
+
+            // Handle each incoming request in a separate thread
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = Arc::clone(&requests);
+            thread::spawn(move || {
+                for mut stream in listener.incoming().flatten() {
+                    // Read the request line and headers
+                    let mut reader = BufReader::new(&stream);
+                    let mut request_line = String::new();
+                    reader
+                        .read_line(&mut request_line)
+                        .expect("Could not read request line");
+                    let mut headers = String::new();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).expect("Could not read header");
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        headers.push_str(&line);
+                    }
+
+                    // Read the body, using the content length
+                    let content_length = headers
+                        .lines()
+                        .find_map(|line| line.strip_prefix("Content-Length: "))
+                        .map_or(0, |length| length.trim().parse().unwrap());
+                    let mut body = vec![0; content_length];
+                    reader.read_exact(&mut body).expect("Could not read body");
+
+                    // Record the request and send the response
+                    let mut parts = request_line.split_whitespace();
+                    let request = WebWorkflowRequest {
+                        method: parts.next().unwrap_or_default().to_owned(),
+                        route: parts.next().unwrap_or_default().to_owned(),
+                        headers,
+                        body,
+                    };
+                    let status = respond(&request);
+                    recorded.lock().unwrap().push(request);
+                    let response = format!(
+                        "HTTP/1.1 {status} Stand-In\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    );
+                    let _ = stream.write_all(response.as_bytes());
+                }
+            });
+
+            WebWorkflowStandIn { port, requests }
+        }
+
+        /// Get the base URL of the stand-in server
+        pub fn url(&self) -> String {
+            format!("http://127.0.0.1:{}", self.port)
+        }
+
+        /// Get the requests received by the stand-in server so far
+        pub fn requests(&self) -> Vec<WebWorkflowRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
 }
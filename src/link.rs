@@ -1,19 +1,172 @@
 // SPDX-FileCopyrightText: 2025 Alec Delaney
 // SPDX-License-Identifier: MIT
 
+use crate::backup;
+use crate::board::DiskSpace;
+use crate::transform::{any_applies, apply_all, Transform, TransformError};
+use crate::web::{WebWorkflow, WebWorkflowError};
 use filetime::{set_file_mtime, FileTime};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
 use std::fs;
 use std::fs::create_dir_all;
 use std::hash::Hash;
+use std::io;
 use std::path::{Path, PathBuf};
 use tabled::Tabled;
 
+/// The suffix of the temporary files that destination files are written to before being
+/// renamed into place
+pub const TEMP_FILE_SUFFIX: &str = ".circpush-tmp";
+
+/// The file operations that can fail while handling file links
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileOperation {
+    ReadMetadata,
+    ReadDirectory,
+    Read,
+    Write,
+    SetModificationTime,
+    CreateDirectory,
+    Delete,
+    Move,
+}
+
+impl fmt::Display for FileOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileOperation::ReadMetadata => write!(f, "read the metadata of"),
+            FileOperation::ReadDirectory => write!(f, "read the directory"),
+            FileOperation::Read => write!(f, "read"),
+            FileOperation::Write => write!(f, "write"),
+            FileOperation::SetModificationTime => write!(f, "set the modification time of"),
+            FileOperation::CreateDirectory => write!(f, "create the directory"),
+            FileOperation::Delete => write!(f, "delete"),
+            FileOperation::Move => write!(f, "move"),
+        }
+    }
+}
+
+/// An I/O error encountered while handling a file, along with the path and operation involved
+///
+/// These can be serialized via JSON so they can be reported to clients.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileIoError {
+    pub operation: FileOperation,
+    pub path: PathBuf,
+    #[serde(with = "error_kind")]
+    pub kind: io::ErrorKind,
+}
+
+impl FileIoError {
+    /// Creates the error for the given I/O error, encountered while performing the given
+    /// operation on the given path
+    pub fn new(operation: FileOperation, path: &Path, error: &io::Error) -> Self {
+        FileIoError {
+            operation,
+            path: path.to_path_buf(),
+            kind: error.kind(),
+        }
+    }
+}
+
+impl fmt::Display for FileIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Could not {} {}: {}",
+            self.operation,
+            self.path.display(),
+            self.kind
+        )
+    }
+}
+
+/// Serialization of I/O error kinds using their names, for the kinds expected when handling files
+mod error_kind {
+
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::io::ErrorKind;
+
+    /// The I/O error kinds that keep their names when serialized and deserialized, with all
+    /// others becoming ErrorKind::Other
+    const KINDS: [ErrorKind; 16] = [
+        ErrorKind::NotFound,
+        ErrorKind::PermissionDenied,
+        ErrorKind::AlreadyExists,
+        ErrorKind::WouldBlock,
+        ErrorKind::NotADirectory,
+        ErrorKind::IsADirectory,
+        ErrorKind::DirectoryNotEmpty,
+        ErrorKind::ReadOnlyFilesystem,
+        ErrorKind::StorageFull,
+        ErrorKind::InvalidInput,
+        ErrorKind::InvalidData,
+        ErrorKind::TimedOut,
+        ErrorKind::Interrupted,
+        ErrorKind::Unsupported,
+        ErrorKind::UnexpectedEof,
+        ErrorKind::OutOfMemory,
+    ];
+
+    pub fn serialize<S: Serializer>(kind: &ErrorKind, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{kind:?}"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ErrorKind, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(KINDS
+            .into_iter()
+            .find(|kind| format!("{kind:?}") == name)
+            .unwrap_or(ErrorKind::Other))
+    }
+}
+
+/// Get an identifier for a file that stays the same when it is renamed or moved within its
+/// file system, if supported by the platform
+fn get_file_id(path: &Path) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata(path)
+            .ok()
+            .map(|metadata| (metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
 /// Get the modification time for a file given the filepath
-fn get_file_mtime(path: &PathBuf) -> FileTime {
-    let metadata = fs::metadata(path).expect("Unable to retrieve file metadata");
-    FileTime::from_last_modification_time(&metadata)
+fn get_file_mtime(path: &Path) -> Result<FileTime, FileIoError> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(FileTime::from_last_modification_time(&metadata)),
+        Err(error) => Err(FileIoError::new(FileOperation::ReadMetadata, path, &error)),
+    }
+}
+
+/// Get the path of the temporary sibling file used when writing the given destination file
+///
+/// The temporary file is hidden, as CircuitPython does not autoreload when hidden files change.
+fn get_temp_path(destination: &Path) -> PathBuf {
+    let filename = destination
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    destination.with_file_name(format!(".{filename}{TEMP_FILE_SUFFIX}"))
+}
+
+/// Get the parent directories of the given path on a board using the web workflow, excluding
+/// the root of the board, in the order they need to be created
+fn get_board_parents(path: &Path) -> Vec<&Path> {
+    let mut parents: Vec<&Path> = path.ancestors().skip(1).collect();
+    parents.pop();
+    parents.reverse();
+    parents
 }
 
 /// FileLink creation errors
@@ -21,23 +174,111 @@ fn get_file_mtime(path: &PathBuf) -> FileTime {
 pub enum FileLinkCreationError {
     InvalidSource,
     InvalidDestination,
-    DestinationSetup,
 }
 
-// FileLink update errors
+/// How file links decide whether their destination files are outdated
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeDetection {
+    /// Compare the modification times of the source and destination files
+    #[default]
+    Mtime,
+    /// Compare the hashes of the contents of the source and destination files
+    Hash,
+}
+
+/// The hash of the contents of a file, along with the size and modification time of the
+/// file when it was hashed
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileDigest {
+    size: u64,
+    modified: FileTime,
+    hash: blake3::Hash,
+}
+
+impl FileDigest {
+    /// Get the hash of the contents of the given file, only re-reading the file if its size
+    /// or modification time differ from those of the given cached digest
+    fn get(path: &Path, cached: &mut Option<FileDigest>) -> Option<blake3::Hash> {
+        // Use the cached hash if the file has not changed since it was hashed
+        let metadata = fs::metadata(path).ok()?;
+        let size = metadata.len();
+        let modified = FileTime::from_last_modification_time(&metadata);
+        if let Some(digest) =
+            cached.filter(|digest| digest.size == size && digest.modified == modified)
+        {
+            return Some(digest.hash);
+        }
+
+        // Otherwise, hash the file contents and cache the result
+        *cached = fs::read(path).ok().map(|contents| FileDigest {
+            size,
+            modified,
+            hash: blake3::hash(&contents),
+        });
+        cached.map(|digest| digest.hash)
+    }
+}
+
+// FileLink update errors
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FileUpdateError {
-    CopyFailed,
+    Io(FileIoError),
+    InsufficientSpace { required: u64, available: u64 },
+    UploadFailed(WebWorkflowError),
+    TransformFailed(TransformError),
 }
 
 /// File link structure for handling the connection between source
 /// and destination filepaths
 ///
 /// These can be serialized into JSON for communication via TCP
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileLink {
     source: PathBuf,
     destination: PathBuf,
+    /// The modification time of the source file when it was last written to a board using the
+    /// web workflow, as the destination file cannot be checked directly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    synced_mtime: Option<(i64, u32)>,
+    /// The hash of the source file when it was last written, for destinations that cannot be
+    /// compared with the source file directly
+    #[serde(skip)]
+    synced_hash: Option<blake3::Hash>,
+    /// The cached hash of the source file, until it changes
+    #[serde(skip)]
+    source_digest: Option<FileDigest>,
+    /// The cached hash of the destination file, until it changes
+    #[serde(skip)]
+    destination_digest: Option<FileDigest>,
+    /// The modification time of the source file when the transforms last failed for it, and
+    /// the error, so that they are not applied again until the source file changes
+    #[serde(skip)]
+    failed_transform: Option<(FileTime, TransformError)>,
+    /// An identifier for the source file that stays the same when it is renamed or moved, if
+    /// supported by the platform
+    #[serde(skip)]
+    source_id: Option<(u64, u64)>,
+    /// The hash of the contents last written to the destination file, used to detect changes
+    /// made on the board before backing it up
+    #[serde(skip)]
+    written_hash: Option<blake3::Hash>,
+    /// The path the transforms are chosen for, which is where the source file is mapped to
+    /// before the transforms change its name, so that the destination name and contents agree
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transform_path: Option<PathBuf>,
+    /// The error encountered the last time the file link was handled, if any, so that it can
+    /// be reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<FileIoError>,
+}
+
+/// Get the modification time for a file as seconds and nanoseconds since the Unix epoch
+fn get_file_mtime_parts(path: &Path) -> Result<(i64, u32), FileIoError> {
+    let mtime = get_file_mtime(path)?;
+    Ok((mtime.unix_seconds(), mtime.nanoseconds()))
 }
 
 impl FileLink {
@@ -64,13 +305,77 @@ impl FileLink {
         let link = FileLink {
             source: source_buf,
             destination: destination_buf,
+            synced_mtime: None,
+            synced_hash: None,
+            source_digest: None,
+            destination_digest: None,
+            failed_transform: None,
+            source_id: get_file_id(source),
+            written_hash: None,
+            transform_path: None,
+            error: None,
+        };
+        Ok(link)
+    }
+
+    /// Create a new FileLink for a destination on a board using the web workflow, given
+    /// the source filepath and the destination path on the board
+    ///
+    /// The source path must be an existing absolute file, and the destination path must
+    /// start at the root of the board.
+    pub fn new_web(source: &Path, destination: &Path) -> Result<Self, FileLinkCreationError> {
+        // If the source path is not an existing file or is not absolute, return an error
+        if !source.is_file() || !source.is_absolute() || source.is_symlink() {
+            return Err(FileLinkCreationError::InvalidSource);
+        }
+
+        // If the destination path does not start at the root of the board, return an error
+        if !destination.has_root() {
+            return Err(FileLinkCreationError::InvalidDestination);
+        }
+
+        // Create and return the FileLink
+        let link = FileLink {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            synced_mtime: None,
+            synced_hash: None,
+            source_digest: None,
+            destination_digest: None,
+            failed_transform: None,
+            source_id: get_file_id(source),
+            written_hash: None,
+            transform_path: None,
+            error: None,
         };
         Ok(link)
     }
 
+    /// Sets the path the transforms are chosen for, rather than the source filepath, such as
+    /// the path the source file is mapped to
+    pub fn with_transform_path(mut self, path: &Path) -> Self {
+        self.transform_path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Get the path the transforms are chosen for
+    fn transform_path(&self) -> &Path {
+        self.transform_path.as_deref().unwrap_or(&self.source)
+    }
+
+    /// Get the source filepath of the file link
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    /// Get the destination filepath of the file link
+    pub fn destination(&self) -> &Path {
+        &self.destination
+    }
+
     /// Ensures that the write path directories exist, such that the source file can eventually be
     /// copied to the required destination
-    pub fn ensure_writepath(&self) -> Result<(), FileLinkCreationError> {
+    pub fn ensure_writepath(&self) -> Result<(), FileIoError> {
         // Skip if the destination already exists
         if !self.destination.as_path().exists() {
             // Check the parent directory of the destination
@@ -80,14 +385,21 @@ impl FileLink {
                 .expect("Could not get the parent of the destination");
 
             // Attempt to create all necessary directories, return an error if unsuccessful
-            if create_dir_all(parent_path).is_err() {
-                return Err(FileLinkCreationError::DestinationSetup);
+            if let Err(error) = create_dir_all(parent_path) {
+                return Err(FileIoError::new(
+                    FileOperation::CreateDirectory,
+                    parent_path,
+                    &error,
+                ));
             }
         }
         Ok(())
     }
 
     /// Checks whether the destination file is outdated
+    ///
+    /// If either modification time cannot be read, the destination file is considered outdated
+    /// so that updating it reports the error.
     pub fn is_outdated(&self) -> bool {
         // If the destination file doesn't exist, it's outdated by definition
         if !self.destination.as_path().exists() {
@@ -95,33 +407,377 @@ impl FileLink {
         }
 
         // Compare the source and destination file modification times
-        let source_mtime = get_file_mtime(&self.source);
-        let destination_mtime = get_file_mtime(&self.destination);
-        source_mtime > destination_mtime
+        match (
+            get_file_mtime(&self.source),
+            get_file_mtime(&self.destination),
+        ) {
+            (Ok(source_mtime), Ok(destination_mtime)) => source_mtime > destination_mtime,
+            _ => true,
+        }
+    }
+
+    /// Checks whether the destination file is outdated by comparing the hashes of the source
+    /// and destination file contents
+    ///
+    /// If any of the given transforms apply to the file link, the destination file contents
+    /// will differ from the source file, so the hash of the source file is compared with that
+    /// of the version last written instead.
+    pub fn is_outdated_hash(&mut self, transforms: &[Transform]) -> bool {
+        let source_hash = FileDigest::get(&self.source, &mut self.source_digest);
+        if any_applies(transforms, self.transform_path()) {
+            return !self.destination.exists() || source_hash != self.synced_hash;
+        }
+        let destination_hash = FileDigest::get(&self.destination, &mut self.destination_digest);
+        source_hash != destination_hash
+    }
+
+    /// Reads the source file and applies the given transforms to its contents, returning both
+    /// the original and the transformed contents
+    ///
+    /// If the transforms already failed for the current version of the source file, they are
+    /// not applied again until the source file changes.
+    fn read_transformed(
+        &mut self,
+        transforms: &[Transform],
+    ) -> Result<(Vec<u8>, Vec<u8>), FileUpdateError> {
+        // Check whether the transforms failed for this version of the source file
+        let source_mtime = get_file_mtime(&self.source).map_err(FileUpdateError::Io)?;
+        if let Some((failed_mtime, error)) = self.failed_transform {
+            if failed_mtime == source_mtime {
+                return Err(FileUpdateError::TransformFailed(error));
+            }
+        }
+
+        // Read and transform the source file contents
+        let contents = fs::read(&self.source).map_err(|error| {
+            FileUpdateError::Io(FileIoError::new(FileOperation::Read, &self.source, &error))
+        })?;
+        match apply_all(transforms, self.transform_path(), contents.clone()) {
+            Ok(transformed) => {
+                self.failed_transform = None;
+                Ok((contents, transformed))
+            }
+            Err(error) => {
+                self.failed_transform = Some((source_mtime, error));
+                Err(FileUpdateError::TransformFailed(error))
+            }
+        }
     }
 
-    /// Updates the file link, copying the source file to the destination
+    /// Updates the file link, copying the source file to the destination after applying
+    /// the given transforms that apply to it
+    ///
+    /// If a backup path is given, the destination file is first saved as a backup there if it
+    /// was changed since it was last written.  If the space of the destination disk is given,
+    /// the source file is checked to fit, and the space is then reduced by the amount written,
+    /// so that it can be reused for a batch of files.
     ///
-    /// Returns the number of bytes copied
-    pub fn update(&mut self) -> Result<u64, FileUpdateError> {
-        // Copy the source file contents to the destination file
-        let amount_copied = match fs::copy(&self.source, &self.destination) {
-            Ok(amount_copied) => amount_copied,
-            Err(_) => return Err(FileUpdateError::CopyFailed),
+    /// Returns the number of bytes written
+    pub fn update(
+        &mut self,
+        transforms: &[Transform],
+        backup_path: Option<&Path>,
+        space: Option<&mut DiskSpace>,
+    ) -> Result<u64, FileUpdateError> {
+        // Transform the source file contents, if needed
+        let transformed = if any_applies(transforms, self.transform_path()) {
+            Some(self.read_transformed(transforms)?)
+        } else {
+            None
+        };
+
+        // Check that the source file will fit on the destination disk, including the space
+        // freed by overwriting the existing destination file, and whether it will fit alongside
+        // the existing destination file
+        let mut fits_alongside = true;
+        let reclaimed = fs::metadata(&self.destination).map_or(0, |metadata| metadata.len());
+        if let Some(space) = &space {
+            let required = match &transformed {
+                Some((_, contents)) => contents.len() as u64,
+                None => fs::metadata(&self.source).map_or(0, |metadata| metadata.len()),
+            };
+            let available = space.available + reclaimed;
+            if required > available {
+                return Err(FileUpdateError::InsufficientSpace {
+                    required,
+                    available,
+                });
+            }
+            fits_alongside = required <= space.available;
+        }
+
+        // Back up the destination file before overwriting it, if requested
+        let mut new_hash = None;
+        if let Some(backup_path) = backup_path {
+            new_hash = match &transformed {
+                Some((_, contents)) => Some(blake3::hash(contents)),
+                None => FileDigest::get(&self.source, &mut self.source_digest),
+            };
+            self.back_up_destination(backup_path, new_hash)?;
+        }
+
+        // Copy the source file contents to the destination file, using a temporary file so that
+        // the destination file is never partially written, unless there is only enough space to
+        // overwrite the destination file directly
+        let write = |path: &Path| match &transformed {
+            Some((_, contents)) => fs::write(path, contents).map(|_| contents.len() as u64),
+            None => fs::copy(&self.source, path),
         };
+        let copy_result = if fits_alongside {
+            self.write_via_temp_file(write)
+        } else {
+            write(&self.destination)
+        };
+        let amount_copied = copy_result.map_err(|error| self.copy_error(&error))?;
+        if let Some(space) = space {
+            space.available = (space.available + reclaimed).saturating_sub(amount_copied);
+        }
 
         // Set the destination file modification time to now
-        let mod_filetime = get_file_mtime(&self.source);
-        set_file_mtime(&self.destination, mod_filetime)
-            .expect("Could not set destination file modification time");
+        let mod_filetime = get_file_mtime(&self.source).map_err(FileUpdateError::Io)?;
+        set_file_mtime(&self.destination, mod_filetime).map_err(|error| {
+            FileUpdateError::Io(FileIoError::new(
+                FileOperation::SetModificationTime,
+                &self.destination,
+                &error,
+            ))
+        })?;
+
+        // Keep track of the hash of the contents written when backing up the destination file,
+        // and of transformed source files that were written, as they cannot be compared with the
+        // destination file
+        self.written_hash = new_hash;
+        if let Some((contents, _)) = &transformed {
+            self.synced_hash = Some(blake3::hash(contents));
+            self.destination_digest = None;
+            return Ok(amount_copied);
+        }
+
+        // Cache the hash of the destination file if the copied source file was already hashed,
+        // so that the destination file does not need to be read back
+        self.destination_digest = match (self.source_digest, fs::metadata(&self.destination)) {
+            (Some(digest), Ok(metadata))
+                if digest.size == amount_copied && digest.modified == mod_filetime =>
+            {
+                Some(FileDigest {
+                    size: metadata.len(),
+                    modified: FileTime::from_last_modification_time(&metadata),
+                    hash: digest.hash,
+                })
+            }
+            _ => None,
+        };
 
         Ok(amount_copied)
     }
 
+    /// Saves the destination file as the next version of the backup at the given backup path,
+    /// unless it does not exist, is unchanged since it was last written, or already has the
+    /// contents with the given hash that are about to be written
+    ///
+    /// If the contents last written are not known, such as after the server is restarted, the
+    /// destination file is assumed to have changed.
+    fn back_up_destination(
+        &mut self,
+        backup_path: &Path,
+        new_hash: Option<blake3::Hash>,
+    ) -> Result<(), FileUpdateError> {
+        let Some(current_hash) = FileDigest::get(&self.destination, &mut self.destination_digest)
+        else {
+            return Ok(());
+        };
+        if Some(current_hash) == self.written_hash || Some(current_hash) == new_hash {
+            return Ok(());
+        }
+        backup::save(&self.destination, backup_path).map_err(FileUpdateError::Io)?;
+        Ok(())
+    }
+
+    /// Converts an I/O error from copying the source file to the destination into an update
+    /// error, blaming the source file if it can no longer be read and the destination otherwise
+    fn copy_error(&self, error: &io::Error) -> FileUpdateError {
+        let io_error = match fs::File::open(&self.source) {
+            Err(source_error) => FileIoError::new(FileOperation::Read, &self.source, &source_error),
+            Ok(_) => FileIoError::new(FileOperation::Write, &self.destination, error),
+        };
+        FileUpdateError::Io(io_error)
+    }
+
+    /// Writes to a temporary sibling file of the destination using the given function, and then
+    /// renames it over the destination file
+    ///
+    /// If the temporary file cannot be renamed over the destination file, it is copied over the
+    /// destination file instead.  The temporary file is removed if anything fails.
+    fn write_via_temp_file(
+        &self,
+        write: impl FnOnce(&Path) -> std::io::Result<u64>,
+    ) -> std::io::Result<u64> {
+        let temp_path = get_temp_path(&self.destination);
+        let result = write(&temp_path).and_then(|amount_copied| {
+            fs::rename(&temp_path, &self.destination)
+                .or_else(|_| fs::copy(&temp_path, &self.destination).map(|_| ()))
+                .map(|_| amount_copied)
+        });
+        if temp_path.exists() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+
     /// Deletes the destination file
     pub fn delete(&self) -> std::io::Result<()> {
         fs::remove_file(&self.destination)
     }
+
+    /// Moves the destination file to the given path, creating its parent directories and
+    /// replacing any existing file at that path
+    pub fn move_destination(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        fs::rename(&self.destination, path)
+    }
+
+    /// Keeps track of when the destination was last written to, along with the cached file
+    /// hashes, from the previous version of the same file link
+    pub fn inherit_sync_state(&mut self, previous: &FileLink) {
+        self.synced_mtime = previous.synced_mtime;
+        self.synced_hash = previous.synced_hash;
+        self.source_digest = previous.source_digest;
+        self.destination_digest = previous.destination_digest;
+        self.failed_transform = previous.failed_transform;
+        self.written_hash = previous.written_hash;
+    }
+
+    /// Checks whether the source file of the given new file link is the source file of this
+    /// file link after being renamed or moved
+    ///
+    /// This is the case if it is the same file, or if it has the same contents as were last
+    /// written to the destination of this file link.  The destination of a board using the
+    /// web workflow cannot be read, so only the contents last written to it are compared.
+    pub fn is_renamed_to(&mut self, new: &mut FileLink, web: bool) -> bool {
+        if self.source_id.is_some() && self.source_id == new.source_id {
+            return true;
+        }
+        let synced_hash = match self.synced_hash {
+            Some(hash) => Some(hash),
+            None if !web => FileDigest::get(&self.destination, &mut self.destination_digest),
+            None => None,
+        };
+        synced_hash.is_some() && synced_hash == FileDigest::get(&new.source, &mut new.source_digest)
+    }
+
+    /// Checks whether the destination file on a board using the web workflow is outdated,
+    /// based on whether the source file has changed since it was last written
+    ///
+    /// If the source modification time cannot be read, the destination file is considered
+    /// outdated so that updating it reports the error.
+    pub fn is_outdated_web(&self) -> bool {
+        match get_file_mtime_parts(&self.source) {
+            Ok(source_mtime) => self.synced_mtime != Some(source_mtime),
+            Err(_) => true,
+        }
+    }
+
+    /// Checks whether the destination file on a board using the web workflow is outdated,
+    /// based on whether the hash of the source file has changed since it was last written
+    pub fn is_outdated_web_hash(&mut self) -> bool {
+        FileDigest::get(&self.source, &mut self.source_digest) != self.synced_hash
+    }
+
+    /// Updates the file link for a board using the web workflow, writing the source file to the
+    /// destination on the board after applying the given transforms that apply to it
+    ///
+    /// Directories already created on the board are tracked using the given set, so that they
+    /// are only created once when writing multiple files.
+    ///
+    /// Returns the number of bytes written
+    pub fn update_web(
+        &mut self,
+        web_workflow: &WebWorkflow,
+        created_directories: &mut HashSet<PathBuf>,
+        transforms: &[Transform],
+    ) -> Result<u64, FileUpdateError> {
+        // Read the source file contents, transforming them if needed
+        let source_mtime = get_file_mtime_parts(&self.source).map_err(FileUpdateError::Io)?;
+        let (contents, transformed) = if any_applies(transforms, self.transform_path()) {
+            let (contents, transformed) = self.read_transformed(transforms)?;
+            (contents, Some(transformed))
+        } else {
+            let contents = fs::read(&self.source).map_err(|error| {
+                FileUpdateError::Io(FileIoError::new(FileOperation::Read, &self.source, &error))
+            })?;
+            (contents, None)
+        };
+        let upload = transformed.as_deref().unwrap_or(&contents);
+
+        // Create the parent directories of the destination if it has not been written before
+        if self.synced_mtime.is_none() {
+            for parent in get_board_parents(&self.destination) {
+                if created_directories.contains(parent) {
+                    continue;
+                }
+                web_workflow
+                    .make_directory(parent)
+                    .map_err(FileUpdateError::UploadFailed)?;
+                created_directories.insert(parent.to_path_buf());
+            }
+        }
+
+        // Write the contents to the destination, keeping the source modification time
+        let timestamp = u128::try_from(source_mtime.0)
+            .ok()
+            .map(|seconds| seconds * 1000 + u128::from(source_mtime.1) / 1_000_000);
+        web_workflow
+            .put_file(&self.destination, upload, timestamp)
+            .map_err(FileUpdateError::UploadFailed)?;
+
+        // Keep track of the source modification time and hash that were written
+        self.synced_mtime = Some(source_mtime);
+        self.synced_hash = Some(blake3::hash(&contents));
+        Ok(upload.len() as u64)
+    }
+
+    /// Deletes the destination file from a board using the web workflow
+    pub fn delete_web(&self, web_workflow: &WebWorkflow) -> Result<(), WebWorkflowError> {
+        web_workflow.delete(&self.destination)
+    }
+
+    /// Moves the destination file on a board using the web workflow to the given path on the
+    /// board, creating its parent directories and replacing any existing file at that path
+    pub fn move_destination_web(
+        &self,
+        web_workflow: &WebWorkflow,
+        path: &Path,
+    ) -> Result<(), WebWorkflowError> {
+        for parent in get_board_parents(path) {
+            web_workflow.make_directory(parent)?;
+        }
+        match web_workflow.move_file(&self.destination, path) {
+            Err(WebWorkflowError::UnexpectedStatus(409)) => {
+                web_workflow.delete(path)?;
+                web_workflow.move_file(&self.destination, path)
+            }
+            result => result,
+        }
+    }
+}
+
+impl PartialEq for FileLink {
+    /// File links are equal if they link the same source and destination, regardless of
+    /// when they were last written
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.destination == other.destination
+    }
+}
+
+impl Eq for FileLink {}
+
+impl Hash for FileLink {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.source.hash(state);
+        self.destination.hash(state);
+    }
 }
 
 impl Tabled for FileLink {
@@ -152,6 +808,7 @@ mod tests {
 
     use super::*;
 
+    use crate::board::disk_space;
     use std::path::absolute;
     use tempfile::{tempdir, NamedTempFile, TempDir};
 
@@ -170,6 +827,15 @@ mod tests {
         let link = FileLink {
             source,
             destination,
+            synced_mtime: None,
+            synced_hash: None,
+            source_digest: None,
+            destination_digest: None,
+            failed_transform: None,
+            source_id: None,
+            written_hash: None,
+            transform_path: None,
+            error: None,
         };
 
         // Return the file link and filepaths
@@ -192,6 +858,15 @@ mod tests {
         let link = FileLink {
             source,
             destination,
+            synced_mtime: None,
+            synced_hash: None,
+            source_digest: None,
+            destination_digest: None,
+            failed_transform: None,
+            source_id: None,
+            written_hash: None,
+            transform_path: None,
+            error: None,
         };
 
         // Return the file link and filepaths
@@ -412,7 +1087,8 @@ mod tests {
                 let error = filelink
                     .ensure_writepath()
                     .expect_err("Successfully ensured an impossible destination");
-                assert_eq!(error, FileLinkCreationError::DestinationSetup);
+                assert_eq!(error.operation, FileOperation::CreateDirectory);
+                assert_eq!(error.path, filelink.destination.parent().unwrap());
 
                 // Check the state of the destination file
                 assert!(filelink.destination.parent().unwrap().is_file());
@@ -433,7 +1109,8 @@ mod tests {
                 let (link, _src, _dst) = create_new_filelink();
 
                 // Set the destination modification time to 30 seconds before the source
-                let orig_mtime = get_file_mtime(&link.source);
+                let orig_mtime =
+                    get_file_mtime(&link.source).expect("Could not get modification time");
                 let new_mtime = FileTime::from_unix_time(
                     orig_mtime.unix_seconds() - 30,
                     orig_mtime.nanoseconds(),
@@ -454,7 +1131,8 @@ mod tests {
                 let (link, _src, _dst) = create_new_filelink();
 
                 // Set the destination modification time to the same time as the source
-                let orig_mtime = get_file_mtime(&link.source);
+                let orig_mtime =
+                    get_file_mtime(&link.source).expect("Could not get modification time");
                 set_file_mtime(&link.destination, orig_mtime)
                     .expect("Could not set modification time");
 
@@ -471,7 +1149,8 @@ mod tests {
                 let (link, _src, _dst) = create_new_filelink();
 
                 // Set the destination modification time to 30 seconds after the source
-                let orig_mtime = get_file_mtime(&link.source);
+                let orig_mtime =
+                    get_file_mtime(&link.source).expect("Could not get modification time");
                 let new_mtime = FileTime::from_unix_time(
                     orig_mtime.unix_seconds() + 30,
                     orig_mtime.nanoseconds(),
@@ -493,6 +1172,102 @@ mod tests {
             }
         }
 
+        mod is_outdated_hash {
+
+            use super::*;
+
+            /// Tests FileLink::is_outdated_hash(), where:
+            ///
+            /// - The destination file has the same contents but an older modification time
+            #[test]
+            fn identical_contents() {
+                // Generate a file link with identical source and destination contents
+                let (mut link, _src, _dst) = create_new_filelink();
+                fs::write(&link.source, "same").expect("Could not write to source file");
+                fs::write(&link.destination, "same").expect("Could not write to destination");
+
+                // Set the destination modification time to 30 seconds before the source
+                let orig_mtime =
+                    get_file_mtime(&link.source).expect("Could not get modification time");
+                let new_mtime = FileTime::from_unix_time(
+                    orig_mtime.unix_seconds() - 30,
+                    orig_mtime.nanoseconds(),
+                );
+                set_file_mtime(&link.destination, new_mtime)
+                    .expect("Could not set modification time");
+
+                // Check the file link is identified as not outdated
+                assert!(link.is_outdated());
+                assert!(!link.is_outdated_hash(&[]));
+            }
+
+            /// Tests FileLink::is_outdated_hash(), where:
+            ///
+            /// - The destination file has different contents but a newer modification time
+            #[test]
+            fn different_contents() {
+                // Generate a file link with different source and destination contents
+                let (mut link, _src, _dst) = create_new_filelink();
+                fs::write(&link.source, "new").expect("Could not write to source file");
+                fs::write(&link.destination, "old").expect("Could not write to destination");
+
+                // Set the destination modification time to 30 seconds after the source
+                let orig_mtime =
+                    get_file_mtime(&link.source).expect("Could not get modification time");
+                let new_mtime = FileTime::from_unix_time(
+                    orig_mtime.unix_seconds() + 30,
+                    orig_mtime.nanoseconds(),
+                );
+                set_file_mtime(&link.destination, new_mtime)
+                    .expect("Could not set modification time");
+
+                // Check the file link is identified as outdated, and is not once updated
+                assert!(!link.is_outdated());
+                assert!(link.is_outdated_hash(&[]));
+                link.update(&[], None, None)
+                    .expect("Could not update file link");
+                assert!(link.destination_digest.is_some());
+                assert!(!link.is_outdated_hash(&[]));
+            }
+
+            /// Tests FileLink::is_outdated_hash(), where:
+            ///
+            /// - The source file changes without its size or modification time changing, so the
+            ///   cached hash is used
+            #[test]
+            fn cached() {
+                // Generate a file link with identical source and destination contents
+                let (mut link, _src, _dst) = create_new_filelink();
+                fs::write(&link.source, "same").expect("Could not write to source file");
+                fs::write(&link.destination, "same").expect("Could not write to destination");
+                assert!(!link.is_outdated_hash(&[]));
+
+                // Change the source contents while keeping its size and modification time
+                let orig_mtime =
+                    get_file_mtime(&link.source).expect("Could not get modification time");
+                fs::write(&link.source, "diff").expect("Could not write to source file");
+                set_file_mtime(&link.source, orig_mtime).expect("Could not set modification time");
+
+                // Check the source file is not re-read until its modification time changes
+                assert!(!link.is_outdated_hash(&[]));
+                let new_mtime = FileTime::from_unix_time(
+                    orig_mtime.unix_seconds() + 30,
+                    orig_mtime.nanoseconds(),
+                );
+                set_file_mtime(&link.source, new_mtime).expect("Could not set modification time");
+                assert!(link.is_outdated_hash(&[]));
+            }
+
+            /// Tests FileLink::is_outdated_hash(), where:
+            ///
+            /// - Destination file does not exist
+            #[test]
+            fn desination_does_not_exist() {
+                let (mut link, _src, _dst) = create_new_unwritten_filelink();
+                assert!(link.is_outdated_hash(&[]))
+            }
+        }
+
         mod update {
 
             use std::io::Write;
@@ -510,8 +1285,15 @@ mod tests {
                 src.write(new_contents)
                     .expect("Could not write to source file");
 
-                // Update the file link
-                let total: u64 = link.update().expect("Could not update file link");
+                // Update the file link, keeping track of the space left on the destination disk
+                let mut space = DiskSpace {
+                    total: 1024,
+                    available: 512,
+                };
+                let total: u64 = link
+                    .update(&[], None, Some(&mut space))
+                    .expect("Could not update file link");
+                assert_eq!(space.available, 512 - new_contents.len() as u64);
 
                 // Get the contents of the source and destination files
                 let src_contents = fs::read(&link.source).expect("Could not read source");
@@ -524,6 +1306,63 @@ mod tests {
                 assert_eq!(total, new_contents.len() as u64);
             }
 
+            /// Tests that FileLink::update() replaces the destination file without leaving the
+            /// temporary file behind
+            #[test]
+            fn temp_file() {
+                // Generate a file link
+                let (mut link, _src, _dst) = create_new_filelink();
+                fs::write(&link.source, "new").expect("Could not write to source file");
+                fs::write(&link.destination, "old").expect("Could not write to destination");
+
+                // Update the file link
+                link.update(&[], None, None)
+                    .expect("Could not update file link");
+
+                // Check the destination was replaced and the temporary file was removed
+                let dst_contents =
+                    fs::read_to_string(&link.destination).expect("Could not read destination");
+                assert_eq!(dst_contents, "new");
+                assert!(!get_temp_path(&link.destination).exists());
+            }
+
+            /// Tests that the destination file is backed up before being overwritten only if it
+            /// was changed since it was last written
+            #[test]
+            fn backup() {
+                // Generate a file link and a backup path
+                let (mut link, _src, _dst) = create_new_filelink();
+                let backup_dir = tempdir().expect("Could not create temporary backup directory");
+                let backup_path = backup_dir.path().join("code.py");
+
+                // Write over a destination file edited on the board, which is backed up
+                fs::write(&link.source, "first").expect("Could not write to source file");
+                fs::write(&link.destination, "edited").expect("Could not write to destination");
+                link.update(&[], Some(&backup_path), None)
+                    .expect("Could not update file link");
+                assert_eq!(backup::versions(&backup_path), vec![1]);
+
+                // Write over the unchanged destination file, which is not backed up
+                fs::write(&link.source, "second").expect("Could not write to source file");
+                link.update(&[], Some(&backup_path), None)
+                    .expect("Could not update file link");
+                assert_eq!(backup::versions(&backup_path), vec![1]);
+
+                // Write over the destination file after it is edited again
+                fs::write(&link.destination, "edited again")
+                    .expect("Could not write to destination");
+                fs::write(&link.source, "third").expect("Could not write to source file");
+                link.update(&[], Some(&backup_path), None)
+                    .expect("Could not update file link");
+                assert_eq!(backup::versions(&backup_path), vec![1, 2]);
+                let backup = fs::read_to_string(backup_dir.path().join("code.py.2"))
+                    .expect("Could not read backup");
+                assert_eq!(backup, "edited again");
+                let dst_contents =
+                    fs::read_to_string(&link.destination).expect("Could not read destination");
+                assert_eq!(dst_contents, "third");
+            }
+
             /// Tests the use case where FileLink::update() would fail
             #[test]
             fn copy_failed() {
@@ -535,9 +1374,85 @@ mod tests {
 
                 // Check that update the file link returns an error
                 let error = link
-                    .update()
+                    .update(&[], None, None)
                     .expect_err("Updated using nonexistent source file");
-                assert_eq!(error, FileUpdateError::CopyFailed);
+                let FileUpdateError::Io(error) = error else {
+                    panic!("Unexpected update error: {error:?}");
+                };
+                assert_eq!(error.operation, FileOperation::Read);
+                assert_eq!(error.path, link.source);
+                assert!(!get_temp_path(&link.destination).exists());
+            }
+
+            /// Tests the use case where the source file would not fit on the destination disk
+            #[test]
+            fn insufficient_space() {
+                // Generate a file link
+                let (mut link, src, dst) = create_new_filelink();
+
+                // Make the source file larger than the available space on the destination disk
+                let mut space =
+                    disk_space(&link.destination).expect("Could not get the disk space");
+                src.as_file()
+                    .set_len(space.available + 1024 * 1024 * 1024)
+                    .expect("Could not resize the source file");
+
+                // Check that updating the file link returns an error and leaves the destination
+                let error = link
+                    .update(&[], None, Some(&mut space))
+                    .expect_err("Updated using a source file that does not fit");
+                assert!(matches!(error, FileUpdateError::InsufficientSpace { .. }));
+                assert_eq!(dst.as_file().metadata().unwrap().len(), 0);
+            }
+        }
+
+        mod web_workflow {
+
+            use super::*;
+
+            use crate::test_support::WebWorkflowStandIn;
+
+            /// Tests creating file links for boards using the web workflow
+            #[test]
+            fn new_web() {
+                let srcfile = NamedTempFile::new().expect("Could not create temporary file");
+                let source = absolute(srcfile.path()).expect("Could not get absolute path");
+                FileLink::new_web(&source, Path::new("/lib/test.py"))
+                    .expect("Could not create file link");
+                let error = FileLink::new_web(&source, Path::new("lib/test.py")).unwrap_err();
+                assert_eq!(error, FileLinkCreationError::InvalidDestination);
+            }
+
+            /// Tests writing a file in a nested directory to a board using the web workflow
+            #[test]
+            fn update_web() {
+                // Generate a file link to a nested directory on the board
+                let srcfile = NamedTempFile::new().expect("Could not create temporary file");
+                fs::write(srcfile.path(), "test").expect("Could not write to source file");
+                let source = absolute(srcfile.path()).expect("Could not get absolute path");
+                let mut link = FileLink::new_web(&source, Path::new("/lib/nested/test.py"))
+                    .expect("Could not create file link");
+                assert!(link.is_outdated_web());
+
+                // Write the file to a stand-in board
+                let stand_in = WebWorkflowStandIn::start(|_| 201);
+                let web = WebWorkflow::new(&stand_in.url(), "").expect("Could not parse URL");
+                let total = link
+                    .update_web(&web, &mut HashSet::new(), &[])
+                    .expect("Could not update file link");
+                assert_eq!(total, 4);
+                assert!(!link.is_outdated_web());
+
+                // Check that the parent directories were created before the file was written
+                let routes: Vec<String> = stand_in
+                    .requests()
+                    .into_iter()
+                    .map(|request| request.route)
+                    .collect();
+                assert_eq!(
+                    routes,
+                    vec!["/fs/lib/", "/fs/lib/nested/", "/fs/lib/nested/test.py"]
+                );
             }
         }
 
@@ -610,4 +1525,45 @@ mod tests {
             }
         }
     }
+
+    mod file_io_error {
+
+        use super::*;
+
+        /// Tests that file I/O errors are described using the operation, path and error kind
+        #[test]
+        fn display() {
+            let error = FileIoError {
+                operation: FileOperation::ReadMetadata,
+                path: PathBuf::from("/test/file.py"),
+                kind: io::ErrorKind::PermissionDenied,
+            };
+            assert_eq!(
+                error.to_string(),
+                "Could not read the metadata of /test/file.py: permission denied"
+            );
+        }
+
+        /// Tests that file I/O errors keep the error kind when serialized and deserialized,
+        /// unless it is not one of the expected kinds
+        #[test]
+        fn serde() {
+            let mut error = FileIoError {
+                operation: FileOperation::Write,
+                path: PathBuf::from("/test/file.py"),
+                kind: io::ErrorKind::StorageFull,
+            };
+            let json = serde_json::to_string(&error).expect("Could not serialize error");
+            assert!(json.contains("\"StorageFull\""));
+            let deserialized: FileIoError =
+                serde_json::from_str(&json).expect("Could not deserialize error");
+            assert_eq!(deserialized, error);
+
+            error.kind = io::ErrorKind::BrokenPipe;
+            let json = serde_json::to_string(&error).expect("Could not serialize error");
+            let deserialized: FileIoError =
+                serde_json::from_str(&json).expect("Could not deserialize error");
+            assert_eq!(deserialized.kind, io::ErrorKind::Other);
+        }
+    }
 }
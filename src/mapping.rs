@@ -0,0 +1,326 @@
+// SPDX-FileCopyrightText: 2025 Alec Delaney
+// SPDX-License-Identifier: MIT
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+
+use regex_automata::meta::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Mapping errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MappingError {
+    /// The mapping is not given as a source and destination separated by "="
+    MissingSeparator,
+    /// A path of the mapping is absolute or leaves its directory
+    InvalidPath(String),
+    /// The regular expression of the rewrite is invalid, along with why
+    InvalidRegex(String),
+}
+
+impl fmt::Display for MappingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MappingError::MissingSeparator => {
+                write!(f, "expected a source and destination separated by '='")
+            }
+            MappingError::InvalidPath(path) => {
+                write!(f, "'{path}' must be a relative path within its directory")
+            }
+            MappingError::InvalidRegex(error) => write!(f, "invalid regular expression: {error}"),
+        }
+    }
+}
+
+/// A rule for where source files are written, overriding their paths relative to the base
+/// directory
+///
+/// Source paths are relative to the base directory, and destination paths relative to the
+/// write directory, both using "/" as the separator.  These can be serialized via JSON so
+/// they are saved along with the file monitors in workspaces.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(tag = "type")]
+pub enum PathMapping {
+    /// Write the given source file to the given destination
+    File {
+        source: PathBuf,
+        destination: PathBuf,
+    },
+    /// Write source files whose paths fully match the regular expression to the replacement,
+    /// which can refer to capture groups (e.g., "$1" or "${name}")
+    Rewrite {
+        pattern: RewritePattern,
+        replacement: String,
+    },
+}
+
+/// The regular expression of a rewrite, compiled once when it is parsed or deserialized
+///
+/// These are serialized as the pattern they were compiled from, and compared by it too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RewritePattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl RewritePattern {
+    /// Compiles the given rewrite pattern, which must match the whole path
+    pub fn new(pattern: &str) -> Result<Self, MappingError> {
+        let regex = Regex::new(&format!("^(?:{pattern})$"))
+            .map_err(|error| MappingError::InvalidRegex(error.to_string()))?;
+        Ok(RewritePattern {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+
+    /// Gets the pattern the regular expression was compiled from
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+}
+
+impl TryFrom<String> for RewritePattern {
+    type Error = MappingError;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        RewritePattern::new(&pattern)
+    }
+}
+
+impl From<RewritePattern> for String {
+    fn from(pattern: RewritePattern) -> Self {
+        pattern.pattern
+    }
+}
+
+impl PartialEq for RewritePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for RewritePattern {}
+
+impl Hash for RewritePattern {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.pattern.hash(state);
+    }
+}
+
+impl PathMapping {
+    /// Parses a file mapping given as "SOURCE=DESTINATION"
+    pub fn parse_file(mapping: &str) -> Result<Self, MappingError> {
+        let (source, destination) = mapping
+            .split_once('=')
+            .ok_or(MappingError::MissingSeparator)?;
+        Ok(PathMapping::File {
+            source: normalize_path(source)?,
+            destination: normalize_path(destination)?,
+        })
+    }
+
+    /// Parses a rewrite given as "PATTERN=REPLACEMENT", checking that the pattern is a valid
+    /// regular expression
+    pub fn parse_rewrite(rewrite: &str) -> Result<Self, MappingError> {
+        let (pattern, replacement) = rewrite
+            .split_once('=')
+            .ok_or(MappingError::MissingSeparator)?;
+        Ok(PathMapping::Rewrite {
+            pattern: RewritePattern::new(pattern)?,
+            replacement: replacement.to_string(),
+        })
+    }
+
+    /// Gets the destination of the given source path if this mapping applies to it, with both
+    /// paths relative to their directories
+    pub fn apply(&self, relative_path: &Path) -> Option<PathBuf> {
+        match self {
+            PathMapping::File {
+                source,
+                destination,
+            } => (source == relative_path).then(|| destination.clone()),
+            PathMapping::Rewrite {
+                pattern,
+                replacement,
+            } => {
+                let regex = &pattern.regex;
+                let haystack = to_slash_path(relative_path);
+                let mut captures = regex.create_captures();
+                regex.captures(&haystack, &mut captures);
+                if !captures.is_match() {
+                    return None;
+                }
+                let destination = captures.interpolate_string(&haystack, replacement);
+                normalize_path(&destination).ok()
+            }
+        }
+    }
+}
+
+impl fmt::Display for PathMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathMapping::File {
+                source,
+                destination,
+            } => write!(
+                f,
+                "{} -> {}",
+                to_slash_path(source),
+                to_slash_path(destination)
+            ),
+            PathMapping::Rewrite {
+                pattern,
+                replacement,
+            } => write!(f, "/{}/ -> {replacement}", pattern.as_str()),
+        }
+    }
+}
+
+/// Gets the destination of the given source path using the first of the given mappings that
+/// applies to it, with both paths relative to their directories
+pub fn map_path(mappings: &[PathMapping], relative_path: &Path) -> Option<PathBuf> {
+    mappings
+        .iter()
+        .find_map(|mapping| mapping.apply(relative_path))
+}
+
+/// Finds the destinations that multiple file mappings write to, along with their sources
+pub fn shared_destinations(mappings: &[PathMapping]) -> Vec<(&Path, Vec<&Path>)> {
+    let mut destinations: Vec<(&Path, Vec<&Path>)> = Vec::new();
+    for mapping in mappings {
+        let PathMapping::File {
+            source,
+            destination,
+        } = mapping
+        else {
+            continue;
+        };
+        match destinations
+            .iter_mut()
+            .find(|(shared, _)| shared == destination)
+        {
+            Some((_, sources)) => sources.push(source),
+            None => destinations.push((destination, vec![source])),
+        }
+    }
+    destinations.retain(|(_, sources)| sources.len() > 1);
+    destinations
+}
+
+/// Converts the given relative path to a string using "/" as the separator
+fn to_slash_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Normalizes the given relative path, checking that it stays within its directory
+fn normalize_path(path: &str) -> Result<PathBuf, MappingError> {
+    let mut normalized = PathBuf::new();
+    for component in Path::new(path.trim()).components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(name) => normalized.push(name),
+            _ => return Err(MappingError::InvalidPath(path.to_string())),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        return Err(MappingError::InvalidPath(path.to_string()));
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    /// Tests parsing and applying file mappings
+    #[test]
+    fn file() {
+        let mapping = PathMapping::parse_file("./apps/thermostat/main.py=code.py")
+            .expect("Could not parse the mapping");
+        assert_eq!(
+            mapping.apply(Path::new("apps/thermostat/main.py")),
+            Some(PathBuf::from("code.py"))
+        );
+        assert_eq!(mapping.apply(Path::new("apps/other/main.py")), None);
+        assert_eq!(mapping.to_string(), "apps/thermostat/main.py -> code.py");
+
+        // Check that invalid mappings are rejected
+        assert_eq!(
+            PathMapping::parse_file("code.py"),
+            Err(MappingError::MissingSeparator)
+        );
+        assert!(PathMapping::parse_file("main.py=../code.py").is_err());
+        assert!(PathMapping::parse_file("main.py=/code.py").is_err());
+    }
+
+    /// Tests parsing and applying rewrites, which must match the whole path
+    #[test]
+    fn rewrite() {
+        let rewrite = PathMapping::parse_rewrite(r"src/(?<name>\w+)/(.*)\.py=lib/${name}/$2.py")
+            .expect("Could not parse the rewrite");
+        assert_eq!(
+            rewrite.apply(Path::new("src/mylib/util/text.py")),
+            Some(PathBuf::from("lib/mylib/util/text.py"))
+        );
+        assert_eq!(rewrite.apply(Path::new("other/src/mylib/text.py")), None);
+
+        // Check that rewrites leaving the write directory do not apply
+        let rewrite =
+            PathMapping::parse_rewrite(r"(.*)\.py=../$1.py").expect("Could not parse the rewrite");
+        assert_eq!(rewrite.apply(Path::new("code.py")), None);
+
+        // Check that invalid regular expressions are rejected
+        assert!(matches!(
+            PathMapping::parse_rewrite("src/(.*=lib/$1"),
+            Err(MappingError::InvalidRegex(_))
+        ));
+    }
+
+    /// Tests that rewrites are serialized as their patterns, and compiled when deserialized
+    #[test]
+    fn serialize_rewrite() {
+        let rewrite = PathMapping::parse_rewrite(r"main_(.*)\.py=code.py")
+            .expect("Could not parse the rewrite");
+        let json = serde_json::to_value(&rewrite).expect("Could not serialize the rewrite");
+        assert_eq!(json["pattern"], r"main_(.*)\.py");
+        let deserialized: PathMapping =
+            serde_json::from_value(json).expect("Could not deserialize the rewrite");
+        assert_eq!(deserialized, rewrite);
+        assert_eq!(
+            deserialized.apply(Path::new("main_clock.py")),
+            Some(PathBuf::from("code.py"))
+        );
+
+        // Check that invalid regular expressions are rejected when deserialized
+        let json = serde_json::json!({"type": "Rewrite", "pattern": "(.*", "replacement": "$1"});
+        assert!(serde_json::from_value::<PathMapping>(json).is_err());
+    }
+
+    /// Tests finding the destinations shared by multiple file mappings
+    #[test]
+    fn shared_destinations() {
+        let mappings = [
+            PathMapping::parse_file("apps/thermostat/main.py=code.py").unwrap(),
+            PathMapping::parse_file("apps/clock/main.py=code.py").unwrap(),
+            PathMapping::parse_file("apps/clock/font.bdf=fonts/clock.bdf").unwrap(),
+        ];
+        assert_eq!(
+            super::shared_destinations(&mappings),
+            vec![(
+                Path::new("code.py"),
+                vec![
+                    Path::new("apps/thermostat/main.py"),
+                    Path::new("apps/clock/main.py")
+                ]
+            )]
+        );
+    }
+}
@@ -1,62 +1,801 @@
 // SPDX-FileCopyrightText: 2025 Alec Delaney
 // SPDX-License-Identifier: MIT
 
-use crate::link::FileLink;
-use glob::glob;
+use crate::backup::get_backup_path;
+use crate::board::{
+    disk_space, find_board_for_path, format_size, is_read_only, BoardInfo, DiskSpace,
+};
+use crate::filter::{FileFilters, IgnoreRules, IGNORE_FILENAMES};
+use crate::link::{
+    ChangeDetection, FileIoError, FileLink, FileOperation, FileUpdateError, TEMP_FILE_SUFFIX,
+};
+use crate::mapping::{map_path, PathMapping};
+use crate::mpy::{is_mpy_file, read_mpy_version};
+use crate::serial::soft_reboot;
+use crate::transform::{output_path, Transform, TransformError};
+use crate::web::{WebWorkflow, WebWorkflowError};
+use glob::{glob, MatchOptions, Pattern};
 use pathdiff::diff_paths;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashSet,
-    env,
+    collections::{BTreeMap, HashSet},
+    env, fmt, fs,
     hash::Hash,
-    path::{absolute, Path, PathBuf},
+    io,
+    path::{absolute, Component, Path, PathBuf},
+    time::{Duration, Instant},
 };
 use tabled::{builder::Builder, Table};
 
+/// The time to wait before retrying a board using the web workflow after a request fails
+const WEB_WORKFLOW_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The time to wait before checking whether a read-only write location is writable again
+const READ_ONLY_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The time to wait before fully updating a file monitor again after it encountered errors
+const ERROR_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The options used when matching source files against glob patterns
+const SOURCE_MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
 /// File monitor update errors
-#[derive(Debug, PartialEq, Eq)]
+///
+/// These can be serialized via JSON so they can be reported to clients.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum UpdateError {
     PartialGlobMatch,
-    FileIOError,
+    NonUnicodePath(PathBuf),
+    NoWritePath(PathBuf),
+    FileIOError(FileIoError),
+    UploadFailed(WebWorkflowError),
     // BadFileLink,
 }
 
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::PartialGlobMatch => write!(f, "Could not match the read pattern"),
+            UpdateError::NonUnicodePath(path) => write!(
+                f,
+                "Could not match the read pattern, as {} is not valid Unicode",
+                path.display()
+            ),
+            UpdateError::NoWritePath(path) => {
+                write!(f, "Could not get the write path for {}", path.display())
+            }
+            UpdateError::FileIOError(error) => write!(f, "{error}"),
+            UpdateError::UploadFailed(error) => write!(f, "Could not update the board: {error}"),
+        }
+    }
+}
+
 /// Path-specific errors
 #[derive(Debug, PartialEq, Eq)]
 pub enum PathError {
     NoRelative,
 }
 
+/// The name of the directory in the write directory that destination files are moved into
+/// when using the trash deletion policy
+pub const TRASH_DIRECTORY_NAME: &str = ".circpush-trash";
+
+/// What happens to destination files when their source files are no longer monitored
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletionPolicy {
+    /// Delete the destination files, mirroring the source files
+    #[default]
+    Mirror,
+    /// Never delete the destination files
+    Keep,
+    /// Move the destination files into a trash directory in the write directory
+    Trash,
+}
+
+/// Reasons a file monitor may be blocked from writing files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BlockedReason {
+    /// The write location is read-only, such as when a board has remounted its storage
+    /// so that only CircuitPython can write to it
+    ReadOnly,
+}
+
+impl fmt::Display for BlockedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockedReason::ReadOnly => write!(f, "read-only"),
+        }
+    }
+}
+
+/// Issues encountered by a file monitor while updating its file links
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum MonitorIssue {
+    /// A source file does not fit in the space available on the destination disk
+    InsufficientSpace {
+        file: PathBuf,
+        required: u64,
+        available: u64,
+    },
+    /// The destination disk is running low on space
+    LowSpace { available: u64, total: u64 },
+    /// A compiled Python file was compiled for a different CircuitPython major version
+    MpyVersionMismatch {
+        file: PathBuf,
+        mpy_version: u8,
+        expected_mpy_version: u8,
+        circuitpython_version: String,
+    },
+    /// A compiled Python file does not have a valid header
+    InvalidMpy { file: PathBuf },
+    /// The board could not be soft rebooted over its serial port
+    SoftRebootFailed { port: PathBuf },
+    /// A file could not be written to a board using the web workflow
+    UploadFailed {
+        file: PathBuf,
+        error: WebWorkflowError,
+    },
+    /// A file could not be transformed before being written
+    TransformFailed {
+        file: PathBuf,
+        error: TransformError,
+    },
+    /// A file was not written because its destination resolves outside the write directory
+    DestinationOutsideWriteDirectory { file: PathBuf },
+    /// A file was not written because other source files have the same destination
+    SharedDestination { file: PathBuf, destination: PathBuf },
+    /// A directory could not be searched for source files, so the files already tracked within
+    /// it are kept as they are
+    ReadFailed { error: FileIoError },
+}
+
+impl MonitorIssue {
+    /// Checks whether the issue is for a source file refused while calculating the tracked
+    /// files, or a directory that could not be read
+    fn is_refusal(&self) -> bool {
+        matches!(
+            self,
+            MonitorIssue::DestinationOutsideWriteDirectory { .. }
+                | MonitorIssue::SharedDestination { .. }
+                | MonitorIssue::ReadFailed { .. }
+        )
+    }
+}
+
+impl fmt::Display for MonitorIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonitorIssue::InsufficientSpace {
+                file,
+                required,
+                available,
+            } => write!(
+                f,
+                "Not enough space to write {} ({} required, {} available)",
+                file.display(),
+                format_size(*required),
+                format_size(*available)
+            ),
+            MonitorIssue::LowSpace { available, total } => write!(
+                f,
+                "Write location is low on space ({} of {} free)",
+                format_size(*available),
+                format_size(*total)
+            ),
+            MonitorIssue::MpyVersionMismatch {
+                file,
+                mpy_version,
+                expected_mpy_version,
+                circuitpython_version,
+            } => write!(
+                f,
+                "{} is mpy version {mpy_version}, but CircuitPython {circuitpython_version} \
+                 can only import mpy version {expected_mpy_version}",
+                file.display()
+            ),
+            MonitorIssue::InvalidMpy { file } => {
+                write!(f, "{} is not a valid .mpy file", file.display())
+            }
+            MonitorIssue::SoftRebootFailed { port } => {
+                write!(f, "Could not soft reboot the board via {}", port.display())
+            }
+            MonitorIssue::UploadFailed { file, error } => {
+                write!(
+                    f,
+                    "Could not write {} to the board: {error}",
+                    file.display()
+                )
+            }
+            MonitorIssue::TransformFailed { file, error } => {
+                write!(f, "Could not transform {}: {error}", file.display())
+            }
+            MonitorIssue::DestinationOutsideWriteDirectory { file } => write!(
+                f,
+                "{} was not written, as its destination resolves outside the write directory",
+                file.display()
+            ),
+            MonitorIssue::SharedDestination { file, destination } => write!(
+                f,
+                "{} was not written, as other source files are also written to {}",
+                file.display(),
+                destination.display()
+            ),
+            MonitorIssue::ReadFailed { error } => write!(f, "{error}"),
+        }
+    }
+}
+
+/// The identity of the CircuitPython board a file monitor writes to
+///
+/// This allows the write directory to be found again if the board is remounted at a
+/// different location.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct BoardBinding {
+    /// The UID of the board, from its boot_out.txt file
+    pub uid: String,
+    /// The write directory relative to the root of the board
+    pub path: PathBuf,
+}
+
+impl BoardBinding {
+    /// Creates the board binding for the given write directory on the given board, if the
+    /// board has a UID to bind to
+    pub fn new(board: &BoardInfo, write_directory: &Path) -> Option<Self> {
+        let uid = board.uid.clone()?;
+        let path = write_directory.strip_prefix(&board.mount_point).ok()?;
+        Some(BoardBinding {
+            uid,
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+/// Find the files matching the given glob pattern, following symlinked files and directories,
+/// along with the errors for any directories that could not be read
+///
+/// Symlinked directories that link back to one of their own parent directories are skipped,
+/// so that cycles are not followed forever.
+fn find_files_following_symlinks(
+    pattern: &str,
+) -> Result<(Vec<PathBuf>, Vec<FileIoError>), UpdateError> {
+    let pattern = Pattern::new(pattern).map_err(|_| UpdateError::PartialGlobMatch)?;
+    let options = SOURCE_MATCH_OPTIONS;
+
+    // Start searching from the leading directories of the pattern without any wildcards, and
+    // only as deep as the pattern can match
+    let (root, max_depth) = split_literal_root(pattern.as_str());
+
+    // Find the matching files
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    if root.is_file() {
+        files.push(root);
+    } else {
+        find_files_in(
+            &root,
+            max_depth,
+            &pattern,
+            options,
+            &mut Vec::new(),
+            &mut files,
+            &mut errors,
+        );
+    }
+    Ok((files, errors))
+}
+
+/// Removes the file links whose destinations are shared with other file links, returning the
+/// issues for their source files
+///
+/// If one of the file links sharing a destination is among the given file links already synced,
+/// it is kept so that its destination is not removed.
+fn refuse_shared_destinations(
+    links: &mut HashSet<FileLink>,
+    synced: &HashSet<FileLink>,
+) -> Vec<MonitorIssue> {
+    // Group the source files by destination, ordered so that the issues are reported in the same
+    // order every time
+    let mut sources: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for link in links.iter() {
+        sources
+            .entry(link.destination().to_path_buf())
+            .or_default()
+            .push(link.source().to_path_buf());
+    }
+    sources.retain(|_, sources| sources.len() > 1);
+    if sources.is_empty() {
+        return Vec::new();
+    }
+    for (destination, files) in &mut sources {
+        files.retain(|file| {
+            !synced
+                .iter()
+                .any(|link| link.source() == file && link.destination() == destination)
+        });
+    }
+    links.retain(|link| {
+        sources
+            .get(link.destination())
+            .is_none_or(|files| !files.iter().any(|file| file == link.source()))
+    });
+
+    sources
+        .into_iter()
+        .flat_map(|(destination, mut files)| {
+            files.sort();
+            files
+                .into_iter()
+                .map(move |file| MonitorIssue::SharedDestination {
+                    file,
+                    destination: destination.clone(),
+                })
+        })
+        .collect()
+}
+
+/// Splits the given glob pattern into its leading path without any wildcards, and how many
+/// components deeper than that path the pattern can match, which is unlimited if the pattern
+/// contains "**"
+fn split_literal_root(pattern: &str) -> (PathBuf, usize) {
+    let components: Vec<_> = Path::new(pattern).components().collect();
+    let literal_count = components
+        .iter()
+        .take_while(|component| {
+            let component = component.as_os_str().to_string_lossy();
+            Pattern::escape(&component) == component
+        })
+        .count();
+    let root: PathBuf = components[..literal_count].iter().collect();
+    let depth = if pattern.contains("**") {
+        usize::MAX
+    } else {
+        components.len() - literal_count
+    };
+    (root, depth)
+}
+
+/// Removes the `.` and `..` components from the given path without resolving symlinks, so that
+/// it matches the paths reported for changes to the files within it
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(component),
+            },
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Recursively find the files in the given directory matching the given pattern, up to the
+/// given depth, following symlinks and skipping directories already among the given ancestors
+///
+/// Errors for directories that could not be read are added to the given errors.
+#[allow(clippy::too_many_arguments)]
+fn find_files_in(
+    directory: &Path,
+    depth: usize,
+    pattern: &Pattern,
+    options: MatchOptions,
+    ancestors: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+    errors: &mut Vec<FileIoError>,
+) {
+    // Skip directories that have already been visited on the way to this one
+    let Ok(canonical_directory) = fs::canonicalize(directory) else {
+        return;
+    };
+    if ancestors.contains(&canonical_directory) {
+        return;
+    }
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(error) => {
+            errors.push(FileIoError::new(
+                FileOperation::ReadDirectory,
+                directory,
+                &error,
+            ));
+            return;
+        }
+    };
+
+    // Search the entries of the directory
+    ancestors.push(canonical_directory);
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            if depth > 1 {
+                find_files_in(&path, depth - 1, pattern, options, ancestors, files, errors);
+            }
+        } else if path.is_file() && pattern.matches_path_with(&path, options) {
+            files.push(path);
+        }
+    }
+    ancestors.pop();
+}
+
+/// Checks whether the given path resolves to a location inside the given canonical directory,
+/// using its nearest existing ancestor if it does not exist yet
+fn resolves_within(path: &Path, directory: &Path) -> bool {
+    for ancestor in path.ancestors() {
+        match fs::canonicalize(ancestor) {
+            Ok(resolved) => return resolved.starts_with(directory),
+            Err(_) if ancestor.is_symlink() => return false,
+            Err(_) => continue,
+        }
+    }
+    false
+}
+
+/// Deserialization of the read patterns of file monitors, which may have been saved as a single
+/// read pattern by previous versions
+mod read_patterns {
+
+    use serde::{Deserialize, Deserializer};
+
+    /// The read patterns as saved
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ReadPatterns {
+        Single(String),
+        Multiple(Vec<String>),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        Ok(match ReadPatterns::deserialize(deserializer)? {
+            ReadPatterns::Single(read_pattern) => vec![read_pattern],
+            ReadPatterns::Multiple(read_patterns) => read_patterns,
+        })
+    }
+}
+
 /// File monitor structure
 ///
-/// Stores a glob pattern to watch for. the base directory from which that
-/// glob pattern should apply, and the write directory where files should
-/// be copied to as the source files are found and updated.
+/// Stores the glob patterns to watch for, the base directory from which those glob patterns
+/// should apply, and the write directory where files should be copied to as the source files
+/// are found and updated.
 ///
 /// These can be serialized via JSON for communication via TCP
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileMonitor {
-    pub read_pattern: String,
+    /// The glob patterns matching the source files, relative to the base directory
+    #[serde(
+        alias = "read_pattern",
+        deserialize_with = "read_patterns::deserialize"
+    )]
+    pub read_patterns: Vec<String>,
+    /// The directory the source files are written to, which is the path on the board for
+    /// boards using the web workflow
     pub write_directory: PathBuf,
+    /// The directory the read patterns are relative to
     pub base_directory: PathBuf,
+    /// The board the write directory is on, so that it can be followed across remounts, and
+    /// changed destination files on it are backed up before being overwritten
+    #[serde(default)]
+    pub board: Option<BoardBinding>,
+    /// The write directory relative to the root of the board, so that the file monitor can be
+    /// used with other boards
+    #[serde(default)]
+    pub destination: Option<PathBuf>,
+    /// The serial port of the board, used to soft reboot it after files are written
+    #[serde(default)]
+    pub serial_port: Option<PathBuf>,
+    /// The board to write the source files to using the web workflow, instead of a disk
+    #[serde(default)]
+    pub web_workflow: Option<WebWorkflow>,
+    /// How the file links decide whether their destination files are outdated
+    #[serde(default)]
+    pub change_detection: ChangeDetection,
+    /// The transforms applied to the source files before they are written, which may also
+    /// change the names of the destination files
+    #[serde(default)]
+    pub transforms: Vec<Transform>,
+    /// Whether symlinked source files and directories are followed
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// What happens to destination files once their source files are removed
+    #[serde(default)]
+    pub deletion_policy: DeletionPolicy,
+    /// The filters for the source files, based on their size and type
+    #[serde(default)]
+    pub filters: FileFilters,
+    /// The glob patterns of source files to exclude, relative to the base directory
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    /// Whether source files listed in `.gitignore` and `.circpushignore` files are excluded
+    #[serde(default)]
+    pub respect_ignore_files: bool,
+    /// The mappings changing where source files are written, where source files written to the
+    /// same destination are refused
+    #[serde(default)]
+    pub mappings: Vec<PathMapping>,
+    /// The file links for the monitored source files
     links: HashSet<FileLink>,
+    /// The issues encountered during the last update of the file links
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<MonitorIssue>,
+    /// Why the file monitor is blocked from writing files, if it is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked: Option<BlockedReason>,
+    /// The error from the last update of the file links, if any, so that it can be reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<UpdateError>,
+    /// When to next try updating the file links, after being blocked or failing to write to a
+    /// board using the web workflow
+    #[serde(skip)]
+    retry_after: Option<Instant>,
+    /// When all of the file links were last re-calculated
+    #[serde(skip)]
+    last_full_update: Option<Instant>,
+    /// When the write directory was last checked to be available, and whether it was
+    #[serde(skip)]
+    target_checked: Option<(Instant, bool)>,
+    /// The rules from the ignore files, kept between updates of changed source files until an
+    /// ignore file changes
+    #[serde(skip)]
+    ignore_rules: Option<IgnoreRules>,
 }
 
 impl FileMonitor {
-    /// Creates a new FileMonitor, given the glob pattern for sources, the base directory,
+    /// Creates a new FileMonitor, given the glob patterns for sources, the base directory,
     /// and relative write directory, with an emptry set of monitored file links
-    pub fn new(read_pattern: &str, write_directory: &Path, base_directory: &Path) -> Self {
+    pub fn new(
+        read_patterns: &[impl AsRef<str>],
+        write_directory: &Path,
+        base_directory: &Path,
+    ) -> Self {
         Self {
-            read_pattern: read_pattern.to_string(),
+            read_patterns: read_patterns
+                .iter()
+                .map(|read_pattern| read_pattern.as_ref().to_string())
+                .collect(),
             write_directory: write_directory.to_path_buf(),
             base_directory: base_directory.to_path_buf(),
+            board: None,
+            destination: None,
+            serial_port: None,
+            web_workflow: None,
+            change_detection: ChangeDetection::default(),
+            transforms: Vec::new(),
+            follow_symlinks: false,
+            deletion_policy: DeletionPolicy::default(),
+            filters: FileFilters::default(),
+            exclude_patterns: Vec::new(),
+            respect_ignore_files: false,
+            mappings: Vec::new(),
             links: HashSet::new(),
+            issues: Vec::new(),
+            blocked: None,
+            error: None,
+            retry_after: None,
+            last_full_update: None,
+            target_checked: None,
+            ignore_rules: None,
+        }
+    }
+
+    /// Creates a new FileMonitor writing to a board using the web workflow, given the glob
+    /// patterns for sources, the base directory, the web workflow connection information, and
+    /// the write directory on the board
+    pub fn new_web(
+        read_patterns: &[impl AsRef<str>],
+        web_workflow: WebWorkflow,
+        write_directory: &Path,
+        base_directory: &Path,
+    ) -> Self {
+        let mut monitor = Self::new(read_patterns, write_directory, base_directory);
+        monitor.web_workflow = Some(web_workflow);
+        monitor
+    }
+
+    /// Get the mount point of the board the file monitor is bound to, based on the
+    /// current write directory
+    fn board_mount_point(&self) -> Option<&Path> {
+        let binding = self.board.as_ref()?;
+        let depth = binding.path.components().count();
+        self.write_directory.ancestors().nth(depth)
+    }
+
+    /// Checks whether the write directory is currently available
+    ///
+    /// For file monitors bound to a board, this checks that the bound board is mounted
+    /// at the expected location.  Other file monitors are always considered available.
+    pub fn is_write_target_available(&self) -> bool {
+        let Some(binding) = &self.board else {
+            return true;
+        };
+        let Some(mount_point) = self.board_mount_point() else {
+            return false;
+        };
+        match BoardInfo::from_mount(mount_point) {
+            Ok(board) => board.uid.as_ref() == Some(&binding.uid),
+            Err(_) => false,
+        }
+    }
+
+    /// Checks whether the write directory is currently available, re-using the result of the
+    /// last check until the given interval has passed, as checking a board reads its
+    /// `boot_out.txt` file
+    pub fn check_write_target_available(&mut self, interval: Duration) -> bool {
+        match self.target_checked {
+            Some((checked, available)) if checked.elapsed() < interval => available,
+            _ => {
+                let available = self.is_write_target_available();
+                self.target_checked = Some((Instant::now(), available));
+                available
+            }
+        }
+    }
+
+    /// Re-resolves the write directory of a file monitor bound to a board, using the
+    /// given list of connected boards
+    ///
+    /// If the board is now mounted somewhere else, the write directory is updated and
+    /// the existing file links are cleared so that they are recalculated for the new
+    /// location.  File monitors with a destination on the board are moved to another
+    /// board if their board is not connected, but only if it is the only board connected,
+    /// and are then bound to that board instead.
+    pub fn resolve_board(&mut self, boards: &[BoardInfo]) {
+        // Get the location of the write directory on the board
+        let Some(board_path) = self.board_path().map(Path::to_path_buf) else {
+            return;
+        };
+
+        // Find the bound board, or the only board connected for file monitors with a destination
+        let bound_board = self.board.as_ref().and_then(|binding| {
+            boards
+                .iter()
+                .find(|board| board.uid.as_ref() == Some(&binding.uid))
+        });
+        let board = match (bound_board, &self.destination, boards) {
+            (Some(board), _, _) | (None, Some(_), [board]) => board,
+            _ => return,
+        };
+        self.set_board(board, &board_path);
+    }
+
+    /// Gets the location of the write directory relative to the root of its board, from the
+    /// destination or the board binding, if the file monitor writes to a board's drive
+    pub fn board_path(&self) -> Option<&Path> {
+        if self.web_workflow.is_some() {
+            return None;
+        }
+        match (&self.destination, &self.board) {
+            (Some(destination), _) => Some(destination),
+            (None, Some(binding)) => Some(&binding.path),
+            (None, None) => None,
+        }
+    }
+
+    /// Writes to the given location relative to the root of the given board, binding the file
+    /// monitor to the board
+    pub fn set_board(&mut self, board: &BoardInfo, board_path: &Path) {
+        let write_directory = board.mount_point.join(board_path);
+        if write_directory != self.write_directory {
+            self.write_directory = write_directory;
+            self.links.clear();
+            self.target_checked = None;
+        }
+        self.board = BoardBinding::new(board, &self.write_directory);
+    }
+
+    /// Checks whether the write location is read-only, blocking the file monitor from writing
+    /// files until it is writable again, and returns whether the file monitor is blocked
+    ///
+    /// This happens when a board's `boot.py` remounts its storage so that CircuitPython can
+    /// write to it, making it read-only to the host.
+    pub fn check_read_only(&mut self) -> bool {
+        if self.web_workflow.is_none() && is_read_only(&self.write_directory) {
+            self.blocked = Some(BlockedReason::ReadOnly);
+            self.retry_after = Some(Instant::now() + READ_ONLY_RETRY_INTERVAL);
+        } else {
+            self.blocked = None;
+        }
+        self.blocked.is_some()
+    }
+
+    /// Removes any temporary files left in the write directory by writes that were interrupted,
+    /// such as by the board being disconnected
+    pub fn remove_temp_files(&self) {
+        if self.web_workflow.is_some() {
+            return;
+        }
+        let Some(write_directory) = self.write_directory.to_str() else {
+            return;
+        };
+        let pattern = format!(
+            "{}/**/.*{TEMP_FILE_SUFFIX}",
+            Pattern::escape(write_directory)
+        );
+        if let Ok(paths) = glob(&pattern) {
+            for path in paths.flatten().filter(|path| path.is_file()) {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    /// Checks the compiled Python files among the given file links against the CircuitPython
+    /// version of the board being written to, returning an issue for each file that the board
+    /// will refuse to import
+    fn check_mpy_links<'a>(&self, links: impl Iterator<Item = &'a FileLink>) -> Vec<MonitorIssue> {
+        // Get the compiled Python file version for the board, if writing to a connected one
+        if self.web_workflow.is_some() {
+            return Vec::new();
+        }
+        let Ok(Some(board)) = find_board_for_path(&self.write_directory) else {
+            return Vec::new();
+        };
+        let Some(expected_mpy_version) = board.mpy_version() else {
+            return Vec::new();
+        };
+
+        // Check the header of each compiled Python file
+        let mut issues = Vec::new();
+        for link in links.filter(|link| is_mpy_file(link.source())) {
+            let file = link.source().to_path_buf();
+            match read_mpy_version(&file) {
+                Ok(mpy_version) if mpy_version != expected_mpy_version => {
+                    issues.push(MonitorIssue::MpyVersionMismatch {
+                        file,
+                        mpy_version,
+                        expected_mpy_version,
+                        circuitpython_version: board.version.clone(),
+                    })
+                }
+                Ok(_) => {}
+                Err(_) => issues.push(MonitorIssue::InvalidMpy { file }),
+            }
         }
+        issues
+    }
+
+    /// Checks the compiled Python files currently matched by the file monitor against the
+    /// CircuitPython version of the board being written to
+    pub fn check_mpy_versions(&self) -> Vec<MonitorIssue> {
+        match self.calculate_monitored_files() {
+            Ok(links) => self.check_mpy_links(links.iter()),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Gets the path a given filepath is written to relative to the write directory, using the
+    /// first mapping that applies to it, before any changes to the filename made by the
+    /// transforms
+    ///
+    /// The transforms applied to the source file are chosen for this path.
+    fn get_mapped_path(&self, filepath: &Path) -> Option<PathBuf> {
+        diff_paths(filepath, &self.base_directory)
+            .map(|relative_path| map_path(&self.mappings, &relative_path).unwrap_or(relative_path))
     }
 
-    /// Gets the write path for a given filepath
-    fn get_write_path(&self, filepath: &PathBuf) -> Result<PathBuf, PathError> {
-        match diff_paths(filepath, &self.base_directory) {
+    /// Gets the write path for a given filepath, using the first mapping that applies to it, and
+    /// including any changes to the filename made by the transforms
+    fn get_write_path(&self, filepath: &Path) -> Result<PathBuf, PathError> {
+        let relative_path = self
+            .get_mapped_path(filepath)
+            .map(|relative_path| output_path(&self.transforms, &relative_path));
+        match relative_path {
+            // Paths on boards using the web workflow are not local, so are used as-is
+            Some(relative_path) if self.web_workflow.is_some() => {
+                Ok(self.write_directory.join(relative_path))
+            }
             Some(relative_path) => {
                 let joinpath = self.write_directory.join(relative_path);
                 Ok(absolute(joinpath).expect("Could not create absolute write path"))
@@ -67,66 +806,667 @@ impl FileMonitor {
 
     /// Calculate the monitored source files, returning an error if the glob match fails
     pub fn calculate_monitored_files(&self) -> Result<HashSet<FileLink>, UpdateError> {
+        self.calculate_links().map(|(links, _)| links)
+    }
+
+    /// Finds the destinations that multiple of the source files currently matched are written
+    /// to, along with those source files, such as when they are rewritten to the same path
+    pub fn find_shared_destinations(&self) -> Result<Vec<(PathBuf, Vec<PathBuf>)>, UpdateError> {
+        let (_, refused) = self.calculate_links()?;
+        let mut destinations: Vec<(PathBuf, Vec<PathBuf>)> = Vec::new();
+        for issue in refused {
+            let MonitorIssue::SharedDestination { file, destination } = issue else {
+                continue;
+            };
+            match destinations
+                .iter_mut()
+                .find(|(shared, _)| *shared == destination)
+            {
+                Some((_, sources)) => sources.push(file),
+                None => destinations.push((destination, vec![file])),
+            }
+        }
+        Ok(destinations)
+    }
+
+    /// Calculate the file links for the monitored source files, along with issues for any files
+    /// refused because their destinations resolve outside the write directory or are shared
+    /// with other files, and for any directories that could not be read
+    fn calculate_links(&self) -> Result<(HashSet<FileLink>, Vec<MonitorIssue>), UpdateError> {
+        // Check that the base directory still exists, as patterns within a removed base
+        // directory would otherwise just match nothing
+        fs::read_dir(&self.base_directory).map_err(|error| {
+            UpdateError::FileIOError(FileIoError::new(
+                FileOperation::ReadDirectory,
+                &self.base_directory,
+                &error,
+            ))
+        })?;
+
+        // Match the files for each of the read patterns, only keeping files matched by multiple
+        // read patterns once, and filter them
+        let mut read_paths = Vec::new();
+        let mut read_errors = Vec::new();
+        for read_pattern in &self.read_patterns {
+            let (paths, errors) = self.find_source_files(read_pattern)?;
+            read_paths.extend(paths);
+            read_errors.extend(errors);
+        }
+        read_paths.sort();
+        read_paths.dedup();
+        let mut ignore_rules = self
+            .respect_ignore_files
+            .then(|| IgnoreRules::load(&self.base_directory));
+        read_paths.retain(|path| {
+            !self.is_excluded(path)
+                && !ignore_rules
+                    .as_mut()
+                    .is_some_and(|rules| rules.is_ignored(path))
+                && self.filters.allows(path)
+        });
+
+        // Get the resolved write directory when following symlinks, so destinations that resolve
+        // outside of it can be refused
+        let resolved_write_directory = match (self.follow_symlinks, &self.web_workflow) {
+            (true, None) => fs::canonicalize(&self.write_directory).ok(),
+            _ => None,
+        };
+
+        // Create the new set of files to return
+        let mut new_hashset = HashSet::new();
+        let mut refused = Vec::new();
+
+        // Iterate through the files matched by the glob pattern, create FileLinks for them, and insert those links into the hash set
+        for read_path in read_paths {
+            // Symlinked source files are resolved, but the destination keeps the symlinked path
+            let abs_read_path = if self.follow_symlinks {
+                match fs::canonicalize(&read_path) {
+                    Ok(resolved_path) => resolved_path,
+                    Err(_) => continue,
+                }
+            } else {
+                absolute(&read_path).map_err(|error| {
+                    UpdateError::FileIOError(FileIoError::new(
+                        FileOperation::ReadMetadata,
+                        &read_path,
+                        &error,
+                    ))
+                })?
+            };
+            let abs_write_path = self
+                .get_write_path(&read_path)
+                .map_err(|_| UpdateError::NoWritePath(read_path.clone()))?;
+            if let Some(write_directory) = &resolved_write_directory {
+                if !resolves_within(&abs_write_path, write_directory) {
+                    refused.push(MonitorIssue::DestinationOutsideWriteDirectory {
+                        file: abs_read_path,
+                    });
+                    continue;
+                }
+            }
+            // Skip source files that were removed since they were matched
+            let mapped_path = self.get_mapped_path(&read_path);
+            if let Some(filelink) =
+                self.create_link(&abs_read_path, &abs_write_path, mapped_path.as_deref())
+            {
+                new_hashset.insert(filelink);
+            }
+        }
+
+        // Keep the tracked files within directories that could not be read, rather than removing
+        // their destinations, and report the directories
+        for error in read_errors {
+            new_hashset.extend(
+                self.links
+                    .iter()
+                    .filter(|link| link.source().starts_with(&error.path))
+                    .cloned(),
+            );
+            refused.push(MonitorIssue::ReadFailed { error });
+        }
+
+        // Refuse source files written to the same destinations, and return the constructed hash set
+        refused.extend(refuse_shared_destinations(&mut new_hashset, &self.links));
+        Ok((new_hashset, refused))
+    }
+
+    /// Finds the source files matched by the given read pattern, following symlinks if requested,
+    /// along with the errors for any directories that could not be read
+    fn find_source_files(
+        &self,
+        read_pattern: &str,
+    ) -> Result<(Vec<PathBuf>, Vec<FileIoError>), UpdateError> {
         // Get the glob pattern as an absolute path string, by joining the pattern with the base directory
-        let abs_read_directory = self.base_directory.join(&self.read_pattern);
-        let read_dir_str = abs_read_directory.to_str().expect("Invalid read directory");
-
-        // Match the glob file found
-        match glob(read_dir_str) {
-            Ok(paths) => {
-                // Create the new set of files to return
-                let mut new_hashset = HashSet::new();
-
-                // Iterate through the files matched by the glob pattern, create FileLinks for them, and insert those links into the hash set
-                for read_path in paths
-                    .map(|result| result.expect("Could not read all glob matches"))
-                    .filter(|path| !path.as_path().is_symlink() && path.as_path().is_file())
-                {
-                    let abs_read_path =
-                        absolute(&read_path).expect("Unable to create absolute path");
-                    let abs_write_path = self
-                        .get_write_path(&read_path)
-                        .expect("Could not get write path wile iterating paths");
-                    let filelink = FileLink::new(&abs_read_path, &abs_write_path)
-                        .expect("Could not create new FileLink");
-                    new_hashset.insert(filelink);
+        let abs_read_directory = self.base_directory.join(read_pattern);
+        let read_dir_str = abs_read_directory
+            .to_str()
+            .ok_or_else(|| UpdateError::NonUnicodePath(self.base_directory.clone()))?;
+
+        // Match the glob file found, following symlinks if requested
+        if self.follow_symlinks {
+            return find_files_following_symlinks(read_dir_str);
+        }
+        let paths = glob(read_dir_str).map_err(|_| UpdateError::PartialGlobMatch)?;
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+        for result in paths {
+            match result {
+                Ok(path) if !path.is_symlink() && path.is_file() => files.push(path),
+                Ok(_) => {}
+                Err(error) => errors.push(FileIoError::new(
+                    FileOperation::ReadDirectory,
+                    error.path(),
+                    error.error(),
+                )),
+            }
+        }
+        Ok((files, errors))
+    }
+
+    /// Creates the file link for the given source and destination paths, if the source is
+    /// still a file, choosing its transforms for the given mapped path if there is one
+    fn create_link(
+        &self,
+        source: &Path,
+        destination: &Path,
+        mapped_path: Option<&Path>,
+    ) -> Option<FileLink> {
+        let link = match self.web_workflow {
+            Some(_) => FileLink::new_web(source, destination),
+            None => FileLink::new(source, destination),
+        }
+        .ok()?;
+        Some(match mapped_path {
+            Some(mapped_path) => link.with_transform_path(mapped_path),
+            None => link,
+        })
+    }
+
+    /// Checks whether the given path is a source file matched by any of the read patterns and
+    /// allowed by the filters and ignore files, without following symlinks
+    fn is_monitored_file(&mut self, path: &Path) -> bool {
+        let is_matched = self.read_patterns.iter().any(|read_pattern| {
+            let pattern = self.base_directory.join(read_pattern);
+            Pattern::new(&pattern.to_string_lossy())
+                .is_ok_and(|pattern| pattern.matches_path_with(path, SOURCE_MATCH_OPTIONS))
+        });
+        is_matched
+            && !path.is_symlink()
+            && path.is_file()
+            && self.filters.allows(path)
+            && !self.is_excluded(path)
+            && !(self.respect_ignore_files
+                && self
+                    .ignore_rules
+                    .get_or_insert_with(|| IgnoreRules::load(&self.base_directory))
+                    .is_ignored(path))
+    }
+
+    /// Checks whether the given path is excluded by any of the exclude patterns
+    ///
+    /// Exclude patterns are relative to the base directory, and also exclude everything within
+    /// the directories they match.  Exclude patterns without any path separators match against
+    /// the names of files and directories at any depth instead (e.g., "__pycache__").
+    fn is_excluded(&self, path: &Path) -> bool {
+        if self.exclude_patterns.is_empty() {
+            return false;
+        }
+        let Some(relative_path) = diff_paths(path, &self.base_directory) else {
+            return false;
+        };
+        self.exclude_patterns
+            .iter()
+            .filter_map(|exclude_pattern| Pattern::new(exclude_pattern).ok())
+            .any(|exclude_pattern| {
+                if exclude_pattern.as_str().contains('/') {
+                    relative_path.ancestors().any(|ancestor| {
+                        exclude_pattern.matches_path_with(ancestor, SOURCE_MATCH_OPTIONS)
+                    })
+                } else {
+                    relative_path.components().any(|component| {
+                        let name = component.as_os_str().to_string_lossy();
+                        exclude_pattern.matches_with(&name, SOURCE_MATCH_OPTIONS)
+                    })
                 }
+            })
+    }
+
+    /// Gets the directories to watch for changes to the source files, and whether their
+    /// subdirectories need to be watched too
+    ///
+    /// Each read pattern is watched from its own directory, skipping directories already
+    /// watched within the directories of other read patterns.
+    pub fn get_watch_roots(&self) -> Vec<(PathBuf, bool)> {
+        let mut watch_roots: Vec<(PathBuf, bool)> = Vec::new();
+        for read_pattern in &self.read_patterns {
+            let pattern = normalize_path(&self.base_directory.join(read_pattern));
+            let (root, depth) = split_literal_root(&pattern.to_string_lossy());
+            let (root, recursive) = match root.parent() {
+                // The read pattern is a single file, so its directory is watched
+                Some(parent) if depth == 0 => (parent.to_path_buf(), false),
+                _ => (root, depth > 1),
+            };
+            match watch_roots.iter_mut().find(|(watched, _)| *watched == root) {
+                Some((_, watched_recursive)) => *watched_recursive |= recursive,
+                None => watch_roots.push((root, recursive)),
+            }
+        }
+        if watch_roots.is_empty() {
+            return vec![(self.base_directory.clone(), false)];
+        }
+        let all_roots = watch_roots.clone();
+        watch_roots.retain(|(root, _)| {
+            !all_roots
+                .iter()
+                .any(|(other, recursive)| *recursive && other != root && root.starts_with(other))
+        });
+        watch_roots
+    }
+
+    /// Checks whether the destination of the given file link is outdated, using the change
+    /// detection mode of the file monitor
+    fn is_link_outdated(&self, link: &mut FileLink) -> bool {
+        match (&self.web_workflow, self.change_detection) {
+            (Some(_), ChangeDetection::Mtime) => link.is_outdated_web(),
+            (Some(_), ChangeDetection::Hash) => link.is_outdated_web_hash(),
+            (None, ChangeDetection::Mtime) => link.is_outdated(),
+            (None, ChangeDetection::Hash) => link.is_outdated_hash(&self.transforms),
+        }
+    }
+
+    /// Writes the source file of the given file link to its destination, keeping track of the
+    /// directories created on boards using the web workflow and of the space left on the
+    /// destination disk otherwise
+    fn write_link(
+        &self,
+        link: &mut FileLink,
+        created_directories: &mut HashSet<PathBuf>,
+        space: Option<&mut DiskSpace>,
+    ) -> Result<u64, FileUpdateError> {
+        match &self.web_workflow {
+            Some(web_workflow) => {
+                link.update_web(web_workflow, created_directories, &self.transforms)
+            }
+            None => {
+                link.ensure_writepath().map_err(FileUpdateError::Io)?;
+                let backup_path = self.get_backup_path(link.destination());
+                link.update(&self.transforms, backup_path.as_deref(), space)
+            }
+        }
+    }
+
+    /// Removes the destination file of the given file link according to the deletion policy
+    ///
+    /// Destination files that no longer exist, such as those already deleted from the board, are
+    /// treated as removed.
+    fn delete_link(&self, link: &FileLink) -> Result<(), UpdateError> {
+        let io_error = |operation| {
+            move |error| {
+                UpdateError::FileIOError(FileIoError::new(operation, link.destination(), &error))
+            }
+        };
+        let result = match (self.deletion_policy, &self.web_workflow) {
+            (DeletionPolicy::Mirror, Some(web_workflow)) => link
+                .delete_web(web_workflow)
+                .map_err(UpdateError::UploadFailed),
+            (DeletionPolicy::Mirror, None) => {
+                link.delete().map_err(io_error(FileOperation::Delete))
+            }
+            (DeletionPolicy::Keep, _) => Ok(()),
+            (DeletionPolicy::Trash, Some(web_workflow)) => link
+                .move_destination_web(web_workflow, &self.get_trash_path(link.destination()))
+                .map_err(UpdateError::UploadFailed),
+            (DeletionPolicy::Trash, None) => link
+                .move_destination(&self.get_trash_path(link.destination()))
+                .map_err(io_error(FileOperation::Move)),
+        };
+        match result {
+            Err(UpdateError::FileIOError(error)) if error.kind == io::ErrorKind::NotFound => Ok(()),
+            Err(UpdateError::UploadFailed(WebWorkflowError::NotFound)) => Ok(()),
+            result => result,
+        }
+    }
 
-                // Return the constructed hash set
-                Ok(new_hashset)
+    /// Renames the destinations of the given removed file links whose source files were renamed
+    /// or moved to become the source files of the given added file links
+    ///
+    /// Renamed file links are taken out of the removed file links and replaced in the stored
+    /// file links, keeping track of when they were last written.  If renaming a destination
+    /// fails, it is deleted and the added file link is written in full instead.
+    fn rename_links(&mut self, removed: &mut Vec<FileLink>, added: Vec<FileLink>) {
+        // Destinations are never removed when keeping them, so they are not renamed either
+        if self.deletion_policy == DeletionPolicy::Keep {
+            return;
+        }
+
+        let web = self.web_workflow.is_some();
+        for mut added_link in added {
+            let Some(index) = removed
+                .iter_mut()
+                .position(|removed_link| removed_link.is_renamed_to(&mut added_link, web))
+            else {
+                continue;
+            };
+            let renamed = match &self.web_workflow {
+                Some(web_workflow) => removed[index]
+                    .move_destination_web(web_workflow, added_link.destination())
+                    .is_ok(),
+                None => removed[index]
+                    .move_destination(added_link.destination())
+                    .is_ok(),
+            };
+            if renamed {
+                let previous = removed.swap_remove(index);
+                self.links.remove(&previous);
+                added_link.inherit_sync_state(&previous);
+                self.links.insert(added_link);
             }
-            Err(_) => Err(UpdateError::PartialGlobMatch),
         }
     }
 
+    /// Gets the path that the given destination file is backed up to before being overwritten,
+    /// keyed by the UID of the board it is on, if the file monitor is bound to a board
+    fn get_backup_path(&self, destination: &Path) -> Option<PathBuf> {
+        let board = self.board.as_ref()?;
+        let relative_path = destination.strip_prefix(&self.write_directory).ok()?;
+        Some(get_backup_path(&board.uid, &board.path.join(relative_path)))
+    }
+
+    /// Gets the path in the trash directory that the given destination file is moved to
+    fn get_trash_path(&self, destination: &Path) -> PathBuf {
+        let trash_directory = self.write_directory.join(TRASH_DIRECTORY_NAME);
+        match destination.strip_prefix(&self.write_directory) {
+            Ok(relative_path) => trash_directory.join(relative_path),
+            Err(_) => trash_directory.join(destination.file_name().unwrap_or_default()),
+        }
+    }
+
+    /// Checks whether the file monitor is ready to update its file links, as it is not waiting
+    /// to retry a board using the web workflow or blocked by a read-only write location
+    fn is_ready_to_update(&mut self) -> bool {
+        // Wait before retrying a board using the web workflow that could not be written to, or
+        // checking whether a read-only write location is writable again
+        if self
+            .retry_after
+            .is_some_and(|retry_after| Instant::now() < retry_after)
+        {
+            return false;
+        }
+        self.retry_after = None;
+        !(self.blocked.is_some() && self.check_read_only())
+    }
+
+    /// Checks whether all of the tracked files should be re-calculated, rather than only the
+    /// changed source files, because they have not been yet or the given interval has passed
+    /// since they last were
+    ///
+    /// File monitors that are waiting to retry, or that encountered errors, are fully updated
+    /// more often.
+    pub fn is_full_update_due(&self, interval: Duration) -> bool {
+        let has_errors = self.error.is_some() || self.links.iter().any(|link| link.error.is_some());
+        let interval = match has_errors {
+            true => interval.min(ERROR_RETRY_INTERVAL),
+            false => interval,
+        };
+        self.last_full_update
+            .is_none_or(|last_full_update| last_full_update.elapsed() >= interval)
+            || self.retry_after.is_some()
+            || self.blocked.is_some()
+    }
+
+    /// Makes the next update re-calculate all of the tracked files
+    pub fn request_full_update(&mut self) {
+        self.last_full_update = None;
+    }
+
     /// Updates the stored file links by re-calculating the tracked files currently
     /// existing and handing the differences from the previously stored links
     pub fn update_links(&mut self) -> Result<(), UpdateError> {
+        // Read the ignore files again when next needed, in case they changed
+        self.ignore_rules = None;
+        if !self.is_ready_to_update() {
+            return Ok(());
+        }
+
         // Re-calculates the tracked files
-        let new_filelinks = self.calculate_monitored_files()?;
+        self.last_full_update = Some(Instant::now());
+        let (new_filelinks, refused) = self.calculate_links()?;
+        self.sync_links(new_filelinks, refused)
+    }
+
+    /// Updates the stored file links for the given changed source file paths only, rather than
+    /// re-calculating all of the tracked files
+    ///
+    /// If any of the changed paths are directories, including a removed base directory, or ignore
+    /// files, or symlinks are followed, the source files affected are not known, so all of the
+    /// tracked files are re-calculated instead.
+    /// This is also the case for read patterns leaving the base directory, as the changed paths
+    /// cannot be matched against them directly, and while any source files are refused or
+    /// directories could not be read, as resolving them may affect other source files.
+    pub fn update_changed_links(&mut self, changed: &[PathBuf]) -> Result<(), UpdateError> {
+        // Ignore changes to excluded paths, such as directories of generated files
+        let changed: Vec<&PathBuf> = changed
+            .iter()
+            .filter(|path| !self.is_excluded(path))
+            .collect();
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        let is_directory_changed = changed.iter().any(|path| {
+            path.is_dir()
+                || self.base_directory.starts_with(path)
+                || self
+                    .links
+                    .iter()
+                    .any(|link| link.source() != *path && link.source().starts_with(path))
+        });
+        let is_ignore_file_changed = self.respect_ignore_files
+            && changed.iter().any(|path| {
+                path.file_name().is_some_and(|filename| {
+                    IGNORE_FILENAMES
+                        .iter()
+                        .any(|ignore_filename| filename == *ignore_filename)
+                }) || path.ends_with(".git/info/exclude")
+            });
+        let has_parent_patterns = self.read_patterns.iter().any(|read_pattern| {
+            Path::new(read_pattern)
+                .components()
+                .any(|component| component == Component::ParentDir)
+        });
+        let has_refusals = self.issues.iter().any(MonitorIssue::is_refusal);
+        if self.follow_symlinks
+            || has_parent_patterns
+            || has_refusals
+            || is_directory_changed
+            || is_ignore_file_changed
+        {
+            return self.update_links();
+        }
+        if !self.is_ready_to_update() {
+            return Ok(());
+        }
 
-        // Handle files that should be deleted
-        for removed_file in self.links.difference(&new_filelinks) {
-            if removed_file.delete().is_err() {
-                return Err(UpdateError::FileIOError);
+        // Replace the file links for the changed source files
+        let mut new_filelinks = self.links.clone();
+        for path in changed {
+            new_filelinks.retain(|link| link.source() != path);
+            if !self.is_monitored_file(path) {
+                continue;
+            }
+            let Ok(write_path) = self.get_write_path(path) else {
+                continue;
+            };
+            let mapped_path = self.get_mapped_path(path);
+            if let Some(filelink) = self.create_link(path, &write_path, mapped_path.as_deref()) {
+                new_filelinks.insert(filelink);
             }
         }
+        let refused = refuse_shared_destinations(&mut new_filelinks, &self.links);
+        self.sync_links(new_filelinks, refused)
+    }
 
-        // Create a list of file links from the hash set
+    /// Handles the differences between the given file links and the previously stored links,
+    /// writing and removing destination files as needed, and then stores the given file links
+    ///
+    /// The given issues for refused source files are recorded too.
+    fn sync_links(
+        &mut self,
+        new_filelinks: HashSet<FileLink>,
+        mut refused: Vec<MonitorIssue>,
+    ) -> Result<(), UpdateError> {
+        // Rename the destinations of source files that were renamed or moved, rather than
+        // deleting them and writing them again
+        let mut removed_files: Vec<FileLink> =
+            self.links.difference(&new_filelinks).cloned().collect();
+        if !removed_files.is_empty() {
+            let added_files = new_filelinks.difference(&self.links).cloned().collect();
+            self.rename_links(&mut removed_files, added_files);
+        }
+
+        // Handle files that should be deleted, blocking the file monitor instead of failing if
+        // the write location has become read-only.  File links whose destinations could not be
+        // removed are kept along with the error, so that removing them is retried on the next
+        // update, and the other files are still handled.
+        let mut undeleted_files = Vec::new();
+        for mut removed_file in removed_files {
+            match self.delete_link(&removed_file) {
+                Ok(()) => continue,
+                Err(_) if self.check_read_only() => return Ok(()),
+                Err(UpdateError::FileIOError(error)) => removed_file.error = Some(error),
+                Err(UpdateError::UploadFailed(error)) => {
+                    refused.push(MonitorIssue::UploadFailed {
+                        file: removed_file.source().to_path_buf(),
+                        error,
+                    });
+                    self.retry_after = Some(Instant::now() + WEB_WORKFLOW_RETRY_INTERVAL);
+                }
+                Err(error) => return Err(error),
+            }
+            undeleted_files.push(removed_file);
+        }
+
+        // Create a list of file links from the hash set, keeping track of when the existing
+        // links were last written
         let mut new_filelinks_vec = Vec::from_iter(new_filelinks);
+        for new_filelink in &mut new_filelinks_vec {
+            if let Some(previous) = self.links.get(new_filelink) {
+                new_filelink.inherit_sync_state(previous);
+            }
+        }
 
         // For re-calculated files, if the destination is outdated, ensure the write path and then
-        // update the destination.
+        // update the destination.  Files that do not fit on the destination disk are recorded as
+        // issues and retried on the next update, and files that could not be written keep the
+        // error until they are.  The space of the destination disk is only looked up once files
+        // need to be written, and is then kept track of for the rest of the batch.
+        let mut issues = Vec::new();
+        let mut batch_space: Option<Option<DiskSpace>> = None;
+        let mut files_written = 0;
+        let mut created_directories = HashSet::new();
         for new_filelink in &mut new_filelinks_vec {
-            if new_filelink.is_outdated() {
-                new_filelink
-                    .ensure_writepath()
-                    .expect("Could not ensure write path");
-                new_filelink
-                    .update()
-                    .expect("Unable to update the file link");
+            if self.is_link_outdated(new_filelink) {
+                let space = batch_space
+                    .get_or_insert_with(|| match self.web_workflow {
+                        Some(_) => None,
+                        None => disk_space(&self.write_directory),
+                    })
+                    .as_mut();
+                match self.write_link(new_filelink, &mut created_directories, space) {
+                    Ok(_) => {
+                        new_filelink.error = None;
+                        files_written += 1;
+                    }
+                    Err(FileUpdateError::InsufficientSpace {
+                        required,
+                        available,
+                    }) => issues.push(MonitorIssue::InsufficientSpace {
+                        file: new_filelink.source().to_path_buf(),
+                        required,
+                        available,
+                    }),
+                    Err(FileUpdateError::TransformFailed(error)) => {
+                        issues.push(MonitorIssue::TransformFailed {
+                            file: new_filelink.source().to_path_buf(),
+                            error,
+                        })
+                    }
+                    Err(FileUpdateError::UploadFailed(error)) => {
+                        // Stop writing to the board for now, as it is likely unreachable
+                        issues.push(MonitorIssue::UploadFailed {
+                            file: new_filelink.source().to_path_buf(),
+                            error,
+                        });
+                        self.retry_after = Some(Instant::now() + WEB_WORKFLOW_RETRY_INTERVAL);
+                        break;
+                    }
+                    Err(FileUpdateError::Io(error)) => {
+                        // Stop writing files if the write location has become read-only
+                        if self.check_read_only() {
+                            break;
+                        }
+                        new_filelink.error = Some(error);
+                    }
+                }
+            }
+        }
+
+        // If any files were written, check whether the destination disk is now low on space and
+        // whether the compiled Python files can be imported.  Otherwise, keep the issues found
+        // when files were last written, for the files still tracked.
+        if let Some(space) = batch_space {
+            issues.extend(self.check_mpy_links(new_filelinks_vec.iter()));
+
+            // Soft reboot the board once the batch of files has been written, if requested
+            if let Some(port) = &self.serial_port {
+                if files_written > 0 && soft_reboot(port).is_err() {
+                    issues.push(MonitorIssue::SoftRebootFailed { port: port.clone() });
+                }
+            }
+
+            if let Some(space) = space {
+                if space.is_low() {
+                    issues.push(MonitorIssue::LowSpace {
+                        available: space.available,
+                        total: space.total,
+                    });
+                }
+            }
+        } else {
+            issues.extend(
+                self.issues
+                    .iter()
+                    .filter(|issue| match issue {
+                        MonitorIssue::LowSpace { .. } | MonitorIssue::SoftRebootFailed { .. } => {
+                            true
+                        }
+                        MonitorIssue::MpyVersionMismatch { file, .. }
+                        | MonitorIssue::InvalidMpy { file } => new_filelinks_vec
+                            .iter()
+                            .any(|new_filelink| new_filelink.source() == file),
+                        _ => false,
+                    })
+                    .cloned(),
+            );
+        }
+
+        // Report any files refused because of their destinations, and any destinations on boards
+        // using the web workflow that could not be removed, replacing the previous issues
+        for issue in refused {
+            if !issues.contains(&issue) {
+                issues.push(issue);
             }
         }
+        self.issues = issues;
+
+        // Keep the file links whose destinations could not be removed, unless their destinations
+        // are now written by other source files
+        undeleted_files.retain(|undeleted_file| {
+            new_filelinks_vec
+                .iter()
+                .all(|new_filelink| new_filelink.destination() != undeleted_file.destination())
+        });
+        new_filelinks_vec.extend(undeleted_files);
 
         // Create the hash set from the newly updated list, and restore it to the FileMonitor
         let new_filelinks = HashSet::from_iter(new_filelinks_vec);
@@ -138,6 +1478,15 @@ impl FileMonitor {
     /// Creates a table record from the FileMonitor for use with tabled, using either relative
     /// or absolute paths
     pub fn to_table_record(&self, absolute: bool) -> Vec<String> {
+        // Show the URL of the write directory for boards using the web workflow
+        if let Some(web_workflow) = &self.web_workflow {
+            let mut record = self.clone_linkless();
+            record.web_workflow = None;
+            let mut fields = record.to_table_record(absolute);
+            fields[2] = web_workflow.display_url(&self.write_directory);
+            return fields;
+        }
+
         // Get the current path and use it to create relative paths for the base and write
         // directories sif requested
         let current_dir = env::current_dir().expect("Could not get current directory");
@@ -166,9 +1515,15 @@ impl FileMonitor {
             write_directory_str = ".";
         }
 
+        // Show the read patterns on separate lines, with the exclude patterns below them
+        let mut read_pattern = self.read_patterns.join("\n");
+        for exclude_pattern in &self.exclude_patterns {
+            read_pattern.push_str(&format!("\n!{exclude_pattern}"));
+        }
+
         // Return the list representation
         vec![
-            self.read_pattern.to_owned(),
+            read_pattern,
             String::from(base_directory_str),
             String::from(write_directory_str),
         ]
@@ -193,15 +1548,40 @@ impl FileMonitor {
     pub fn clone_linkless(&self) -> Self {
         let mut linkless = self.clone();
         linkless.links.clear();
+        linkless.issues.clear();
+        linkless.blocked = None;
+        linkless.error = None;
         linkless
     }
+
+    /// Gets the errors encountered the last time the file links were handled, sorted by path
+    pub fn link_errors(&self) -> Vec<&FileIoError> {
+        let mut errors: Vec<_> = self
+            .links
+            .iter()
+            .filter_map(|link| link.error.as_ref())
+            .collect();
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        errors
+    }
 }
 
 impl PartialEq for FileMonitor {
     fn eq(&self, other: &Self) -> bool {
-        self.read_pattern == other.read_pattern
+        self.read_patterns == other.read_patterns
             && self.write_directory == other.write_directory
             && self.base_directory == other.base_directory
+            && self.board == other.board
+            && self.destination == other.destination
+            && self.web_workflow == other.web_workflow
+            && self.change_detection == other.change_detection
+            && self.transforms == other.transforms
+            && self.follow_symlinks == other.follow_symlinks
+            && self.deletion_policy == other.deletion_policy
+            && self.filters == other.filters
+            && self.exclude_patterns == other.exclude_patterns
+            && self.respect_ignore_files == other.respect_ignore_files
+            && self.mappings == other.mappings
     }
 }
 
@@ -209,9 +1589,20 @@ impl Eq for FileMonitor {}
 
 impl Hash for FileMonitor {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.read_pattern.hash(state);
+        self.read_patterns.hash(state);
         self.write_directory.hash(state);
         self.base_directory.hash(state);
+        self.board.hash(state);
+        self.destination.hash(state);
+        self.web_workflow.hash(state);
+        self.change_detection.hash(state);
+        self.transforms.hash(state);
+        self.follow_symlinks.hash(state);
+        self.deletion_policy.hash(state);
+        self.filters.hash(state);
+        self.exclude_patterns.hash(state);
+        self.respect_ignore_files.hash(state);
+        self.mappings.hash(state);
     }
 }
 
@@ -267,10 +1658,29 @@ mod tests {
 
             // Create the file monitor
             let monitor = FileMonitor {
-                read_pattern: read_pattern.to_string(),
+                read_patterns: vec![read_pattern.to_string()],
                 write_directory: write_directory.path().to_path_buf(),
                 base_directory: read_directory.path().to_path_buf(),
+                board: None,
+                destination: None,
+                serial_port: None,
+                web_workflow: None,
+                change_detection: ChangeDetection::default(),
+                transforms: Vec::new(),
+                follow_symlinks: false,
+                deletion_policy: DeletionPolicy::default(),
+                filters: FileFilters::default(),
+                exclude_patterns: Vec::new(),
+                respect_ignore_files: false,
+                mappings: Vec::new(),
                 links: HashSet::new(),
+                issues: Vec::new(),
+                blocked: None,
+                error: None,
+                retry_after: None,
+                last_full_update: None,
+                target_checked: None,
+                ignore_rules: None,
             };
 
             // Return the file monitor and temporary read and write directories
@@ -284,16 +1694,89 @@ mod tests {
             let read_pattern = "test_file";
             let write_directory = TempDir::new().expect("Could not get temporary directory");
             let base_directory = TempDir::new().expect("Could not get temporary directory");
-            let monitor =
-                FileMonitor::new(&read_pattern, write_directory.path(), base_directory.path());
+            let monitor = FileMonitor::new(
+                &[read_pattern],
+                write_directory.path(),
+                base_directory.path(),
+            );
 
             // Check the fields of the file monitor
-            assert_eq!(monitor.read_pattern, read_pattern);
+            assert_eq!(monitor.read_patterns, vec![read_pattern]);
             assert_eq!(monitor.write_directory, write_directory.into_path());
             assert_eq!(monitor.base_directory, base_directory.into_path());
             assert!(monitor.links.is_empty());
         }
 
+        /// Tests deserializing a file monitor saved with a single read pattern
+        #[test]
+        fn deserialize_read_pattern() {
+            let json = r#"{
+                "read_pattern": "test*",
+                "write_directory": "/write",
+                "base_directory": "/base",
+                "links": []
+            }"#;
+            let monitor: FileMonitor =
+                serde_json::from_str(json).expect("Could not deserialize the file monitor");
+            assert_eq!(monitor.read_patterns, vec![String::from("test*")]);
+
+            // Check that the read patterns are saved as a list
+            let json = serde_json::to_value(&monitor).expect("Could not serialize file monitor");
+            assert_eq!(json["read_patterns"], serde_json::json!(["test*"]));
+        }
+
+        /// Tests getting the directories to watch for the source files of multiple read patterns
+        #[test]
+        fn get_watch_roots() {
+            let base_directory = Path::new("/base");
+            let monitor = FileMonitor::new(&["*.py"], Path::new("/write"), base_directory);
+            assert_eq!(
+                monitor.get_watch_roots(),
+                vec![(base_directory.to_path_buf(), false)]
+            );
+            let monitor = FileMonitor::new(&["*.py", "*.txt"], Path::new("/write"), base_directory);
+            assert_eq!(
+                monitor.get_watch_roots(),
+                vec![(base_directory.to_path_buf(), false)]
+            );
+            let monitor = FileMonitor::new(
+                &["lib/*.mpy", "lib/fonts/*.bdf"],
+                Path::new("/write"),
+                base_directory,
+            );
+            assert_eq!(
+                monitor.get_watch_roots(),
+                vec![
+                    (base_directory.join("lib"), false),
+                    (base_directory.join("lib/fonts"), false)
+                ]
+            );
+            let monitor = FileMonitor::new(
+                &["lib/**/*.mpy", "lib/fonts/*.bdf"],
+                Path::new("/write"),
+                base_directory,
+            );
+            assert_eq!(
+                monitor.get_watch_roots(),
+                vec![(base_directory.join("lib"), true)]
+            );
+
+            // Check that read patterns in unrelated directories are watched separately
+            let monitor = FileMonitor::new(
+                &["*.py", "/other/**/*.py", "../shared/./lib/*.mpy"],
+                Path::new("/write"),
+                base_directory,
+            );
+            assert_eq!(
+                monitor.get_watch_roots(),
+                vec![
+                    (base_directory.to_path_buf(), false),
+                    (PathBuf::from("/other"), true),
+                    (PathBuf::from("/shared/lib"), false)
+                ]
+            );
+        }
+
         mod get_write_path {
 
             use super::*;
@@ -362,11 +1845,183 @@ mod tests {
                     let read_filepath = read_dir.path().join(&filename);
                     let write_filepath = write_dir.path().join(&filename);
 
-                    // Check that the corresponding file link is contained in the files claculated
-                    let link = FileLink::new(&read_filepath, &write_filepath)
-                        .expect("Could not create file link");
-                    assert!(files.contains(&link));
-                }
+                    // Check that the corresponding file link is contained in the files claculated
+                    let link = FileLink::new(&read_filepath, &write_filepath)
+                        .expect("Could not create file link");
+                    assert!(files.contains(&link));
+                }
+            }
+
+            /// Tests FileMonitor::calculate_monitored_files() with multiple read patterns, some of
+            /// which match the same files
+            #[test]
+            fn read_patterns() {
+                // Generate a file monitor with overlapping read patterns
+                let (mut monitor, read_dir, _write_dir) = get_monitor();
+                monitor.read_patterns = vec![
+                    String::from("test_file[01]"),
+                    String::from("*1"),
+                    String::from("test_file3"),
+                ];
+
+                // Check that each matched file is only monitored once
+                let files = monitor
+                    .calculate_monitored_files()
+                    .expect("Could not calculate the monitored files");
+                let mut sources: Vec<_> = files.iter().map(|link| link.source()).collect();
+                sources.sort();
+                let expected: Vec<_> = ["test_file0", "test_file1", "test_file3"]
+                    .iter()
+                    .map(|filename| read_dir.path().join(filename))
+                    .collect();
+                assert_eq!(sources, expected);
+
+                // Check that the read patterns are shown together
+                let record = monitor.to_table_record(true);
+                assert_eq!(record[0], "test_file[01]\n*1\ntest_file3");
+            }
+
+            /// Tests FileMonitor::calculate_monitored_files() with mappings, refusing source files
+            /// mapped to the same destination
+            #[test]
+            fn mappings() {
+                // Generate a file monitor mapping one file, rewriting the rest, and mapping a
+                // file to the rewritten destination of another
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                monitor.mappings = vec![
+                    PathMapping::parse_file("test_file0=code.py").unwrap(),
+                    PathMapping::parse_file("test_file2=lib/file1").unwrap(),
+                    PathMapping::parse_rewrite(r"test_file(\d)=lib/file$1").unwrap(),
+                ];
+
+                // Check the destinations of the files written
+                let (files, refused) = monitor
+                    .calculate_links()
+                    .expect("Could not calculate the monitored files");
+                let mut destinations: Vec<_> = files
+                    .iter()
+                    .map(|link| link.destination().strip_prefix(write_dir.path()).unwrap())
+                    .collect();
+                destinations.sort();
+                assert_eq!(
+                    destinations,
+                    vec![Path::new("code.py"), Path::new("lib/file3")]
+                );
+
+                // Check that the files mapped to the same destination are refused
+                let destination = write_dir.path().join("lib/file1");
+                let expected: Vec<_> = ["test_file1", "test_file2"]
+                    .iter()
+                    .map(|filename| MonitorIssue::SharedDestination {
+                        file: read_dir.path().join(filename),
+                        destination: destination.clone(),
+                    })
+                    .collect();
+                assert_eq!(refused, expected);
+
+                // Check that the shared destination is found along with its source files
+                assert_eq!(
+                    monitor
+                        .find_shared_destinations()
+                        .expect("Could not find shared destinations"),
+                    vec![(
+                        destination,
+                        vec![
+                            read_dir.path().join("test_file1"),
+                            read_dir.path().join("test_file2")
+                        ]
+                    )]
+                );
+            }
+
+            /// Tests FileMonitor::calculate_monitored_files() where source files are excluded
+            #[test]
+            fn exclude_patterns() {
+                // Generate a file monitor for Python files, excluding tests and cached files
+                let (mut monitor, read_dir, _write_dir) = get_monitor();
+                monitor.read_patterns = vec![String::from("src/**/*.py")];
+                monitor.exclude_patterns = vec![
+                    String::from("src/**/test_*.py"),
+                    String::from("__pycache__"),
+                ];
+                for filename in [
+                    "src/code.py",
+                    "src/test_code.py",
+                    "src/lib/module.py",
+                    "src/lib/test_module.py",
+                    "src/lib/__pycache__/module.py",
+                ] {
+                    let path = read_dir.path().join(filename);
+                    fs::create_dir_all(path.parent().unwrap()).expect("Could not create directory");
+                    fs::write(&path, "").expect("Could not write source file");
+                }
+
+                // Check that only the files not excluded are monitored
+                let files = monitor
+                    .calculate_monitored_files()
+                    .expect("Could not calculate the monitored files");
+                let mut sources: Vec<_> = files
+                    .iter()
+                    .map(|link| link.source().strip_prefix(read_dir.path()).unwrap())
+                    .collect();
+                sources.sort();
+                assert_eq!(
+                    sources,
+                    vec![Path::new("src/code.py"), Path::new("src/lib/module.py")]
+                );
+
+                // Check that the exclude patterns are shown with the read pattern
+                let record = monitor.to_table_record(true);
+                assert_eq!(record[0], "src/**/*.py\n!src/**/test_*.py\n!__pycache__");
+            }
+
+            /// Tests FileMonitor::calculate_monitored_files() where source files are ignored by
+            /// ignore files
+            #[test]
+            fn respect_ignore_files() {
+                // Generate a file monitor for Python files in a project with ignore files
+                let (mut monitor, read_dir, _write_dir) = get_monitor();
+                monitor.read_patterns = vec![String::from("**/*.py")];
+                fs::write(read_dir.path().join(".gitignore"), "build/\n")
+                    .expect("Could not write ignore file");
+                fs::write(read_dir.path().join(".circpushignore"), "test_*.py\n")
+                    .expect("Could not write ignore file");
+                for filename in ["code.py", "test_code.py", "build/code.py"] {
+                    let path = read_dir.path().join(filename);
+                    fs::create_dir_all(path.parent().unwrap()).expect("Could not create directory");
+                    fs::write(&path, "").expect("Could not write source file");
+                }
+
+                // Check that the ignore files are only used if requested
+                let files = monitor
+                    .calculate_monitored_files()
+                    .expect("Could not calculate the monitored files");
+                assert_eq!(files.len(), 3);
+                monitor.respect_ignore_files = true;
+                let files = monitor
+                    .calculate_monitored_files()
+                    .expect("Could not calculate the monitored files");
+                let sources: Vec<_> = files.iter().map(|link| link.source()).collect();
+                assert_eq!(sources, vec![read_dir.path().join("code.py")]);
+            }
+
+            /// Tests FileMonitor::calculate_monitored_files() where the source files are filtered
+            #[test]
+            fn filters() {
+                // Generate a file monitor only monitoring files of at least a given size
+                let (mut monitor, read_dir, _write_dir) = get_monitor();
+                monitor.filters.min_size = Some(1);
+                fs::write(read_dir.path().join("test_file2"), "data")
+                    .expect("Could not write to source file");
+
+                // Check that only the large enough file is monitored
+                let files = monitor
+                    .calculate_monitored_files()
+                    .expect("Could not calculate the monitored files");
+                assert_eq!(files.len(), 1);
+                assert!(files
+                    .iter()
+                    .all(|link| link.source().ends_with("test_file2")));
             }
 
             /// Tests the unsuccessful use case of FileMonitor::calculate_monitored_files()
@@ -376,7 +2031,7 @@ mod tests {
                 let (mut monitor, _read_dir, _write_dir) = get_monitor();
 
                 // Set the file monitor read pattern to a bad regex
-                monitor.read_pattern = String::from("text[text");
+                monitor.read_patterns = vec![String::from("text[text")];
 
                 // Check that calculating the monitored files causes an error
                 let error = monitor
@@ -629,35 +2284,697 @@ mod tests {
 
             /// Tests FileMonitor::update_links(), where:
             ///
-            /// - A source file deletion results in trying to delete a nonexistent destination file
+            /// - A source file deletion results in trying to delete a destination file that was
+            ///   already deleted, which is treated as removed while new files are still written
             #[test]
-            fn file_io_error() {
+            fn deletion_not_found() {
                 // Generate a file monitor
                 let (mut monitor, read_dir, write_dir) = get_monitor();
 
                 // Get the read and write paths for the test file
                 let filename = "test_file4";
-                let read_file = read_dir.path().join(&filename);
-                let write_file = write_dir.path().join(&filename);
+                let read_file = read_dir.path().join(filename);
+                let write_file = write_dir.path().join(filename);
 
-                // Create the new read path file
+                // Create a file link for a source file that was deleted from both sides
                 fs::File::create_new(&read_file).expect("Could not create file");
-
-                // Create a new file link for the read path
                 let link =
                     FileLink::new(&read_file, &write_file).expect("Could not create file link");
-
-                // Delete the created read path file
                 fs::remove_file(&read_file).expect("Could not delete file");
-
-                // Insert the file link as a monitored link
                 monitor.links.insert(link);
 
-                // Check that updating the links causes an error
-                let error = monitor
+                // Check that updating the links succeeds, and that the other files are written
+                monitor
                     .update_links()
-                    .expect_err("Successfully updated broken link");
-                assert_eq!(error, UpdateError::FileIOError);
+                    .expect("Unable to update links after the destination was deleted");
+                assert!(monitor.links.iter().all(|link| link.source() != read_file));
+                assert!(monitor.link_errors().is_empty());
+                assert!(write_dir.path().join("test_file1").is_file());
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - A destination file cannot be deleted, which is recorded on its file link while
+            ///   the other files are still written
+            #[test]
+            fn deletion_error() {
+                // Generate a file monitor
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+
+                // Get the read and write paths for the test file
+                let filename = "test_file4";
+                let read_file = read_dir.path().join(filename);
+                let write_file = write_dir.path().join(filename);
+
+                // Create a file link for a deleted source file, whose destination is a directory
+                // that cannot be deleted as a file
+                fs::File::create_new(&read_file).expect("Could not create file");
+                let link =
+                    FileLink::new(&read_file, &write_file).expect("Could not create file link");
+                fs::remove_file(&read_file).expect("Could not delete file");
+                fs::create_dir(&write_file).expect("Could not create directory");
+                monitor.links.insert(link);
+
+                // Check that updating the links succeeds, and that the other files are written
+                monitor.update_links().expect("Unable to update links");
+                assert!(write_dir.path().join("test_file1").is_file());
+
+                // Check that the error is recorded on the kept file link
+                let errors = monitor.link_errors();
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].operation, FileOperation::Delete);
+                assert_eq!(errors[0].path, write_file);
+
+                // Check that the file link is dropped once its destination is removed
+                fs::remove_dir(&write_file).expect("Could not delete directory");
+                monitor.update_links().expect("Unable to update links");
+                assert!(monitor.links.iter().all(|link| link.source() != read_file));
+                assert!(monitor.link_errors().is_empty());
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - A tracked file cannot be written, which is recorded on its file link while the
+            ///   other files are still written
+            #[test]
+            fn link_write_error() {
+                // Generate a file monitor tracking a file in a nested directory too
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                monitor.read_patterns = vec![String::from("**/test*")];
+                let nested_read_dir = read_dir.path().join("nested");
+                fs::create_dir(&nested_read_dir).expect("Could not create nested directory");
+                fs::File::create_new(nested_read_dir.join("test_file4"))
+                    .expect("Could not create file");
+
+                // Block the nested write directory with a file
+                let nested_write_dir = write_dir.path().join("nested");
+                fs::File::create_new(&nested_write_dir).expect("Could not create file");
+
+                // Check that updating the links succeeds for the other files
+                monitor.update_links().expect("Could not update links");
+                for i in 0..4 {
+                    assert!(write_dir.path().join(format!("test_file{i}")).is_file());
+                }
+
+                // Check that the error is recorded on the file link
+                let errors = monitor.link_errors();
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].operation, FileOperation::CreateDirectory);
+                assert_eq!(errors[0].path, nested_write_dir);
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - A new source file is mapped to the destination of a file already written, which
+            ///   is kept while the new source file is refused
+            #[test]
+            fn shared_destination_kept() {
+                // Generate a file monitor writing one file as code.py
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                fs::write(read_dir.path().join("test_file0"), "synced")
+                    .expect("Could not write file");
+                monitor.mappings = vec![PathMapping::parse_file("test_file0=code.py").unwrap()];
+                monitor.update_links().expect("Could not update links");
+                let code_file = write_dir.path().join("code.py");
+                assert_eq!(fs::read_to_string(&code_file).unwrap(), "synced");
+
+                // Rewrite another file to code.py too
+                monitor
+                    .mappings
+                    .push(PathMapping::parse_rewrite(r"test_file1=code.py").unwrap());
+                monitor.update_links().expect("Could not update links");
+
+                // Check that the file already written is kept, and only the new file is refused
+                assert_eq!(fs::read_to_string(&code_file).unwrap(), "synced");
+                assert!(monitor
+                    .links
+                    .iter()
+                    .any(|link| link.destination() == code_file));
+                assert_eq!(
+                    monitor
+                        .issues
+                        .iter()
+                        .filter(|issue| matches!(issue, MonitorIssue::SharedDestination { .. }))
+                        .collect::<Vec<_>>(),
+                    vec![&MonitorIssue::SharedDestination {
+                        file: read_dir.path().join("test_file1"),
+                        destination: code_file,
+                    }]
+                );
+            }
+
+            /// Tests FileMonitor::update_changed_links(), where:
+            ///
+            /// - A refused source file is deleted, which clears its issue without any files
+            ///   being written
+            #[test]
+            fn resolved_issue_cleared() {
+                // Generate a file monitor with two files mapped to code.py, one already written
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                monitor.mappings = vec![PathMapping::parse_file("test_file0=code.py").unwrap()];
+                monitor.update_links().expect("Could not update links");
+                monitor
+                    .mappings
+                    .push(PathMapping::parse_file("test_file1=code.py").unwrap());
+                monitor.update_links().expect("Could not update links");
+                assert!(monitor
+                    .issues
+                    .iter()
+                    .any(|issue| matches!(issue, MonitorIssue::SharedDestination { .. })));
+
+                // Check that deleting the refused source file clears the issue
+                let code_mtime = fs::metadata(write_dir.path().join("code.py"))
+                    .and_then(|metadata| metadata.modified())
+                    .expect("Could not get the modification time of code.py");
+                let refused_file = read_dir.path().join("test_file1");
+                fs::remove_file(&refused_file).expect("Could not delete file");
+                monitor
+                    .update_changed_links(&[refused_file])
+                    .expect("Could not update links");
+                assert!(monitor.issues.is_empty());
+                assert_eq!(
+                    fs::metadata(write_dir.path().join("code.py"))
+                        .and_then(|metadata| metadata.modified())
+                        .expect("Could not get the modification time of code.py"),
+                    code_mtime
+                );
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - A source directory cannot be read, which is reported while the files already
+            ///   written from it are kept and the other files are still written
+            #[cfg(unix)]
+            #[test]
+            fn unreadable_directory() {
+                use std::os::unix::fs::PermissionsExt;
+
+                // Generate a file monitor tracking a file in a nested directory too
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                monitor.read_patterns = vec![String::from("**/test*")];
+                let nested_read_dir = read_dir.path().join("nested");
+                fs::create_dir(&nested_read_dir).expect("Could not create nested directory");
+                fs::File::create_new(nested_read_dir.join("test_file4"))
+                    .expect("Could not create file");
+                monitor.update_links().expect("Could not update links");
+                let nested_write_file = write_dir.path().join("nested").join("test_file4");
+                assert!(nested_write_file.is_file());
+
+                // Make the nested directory unreadable, which has no effect when run as root
+                fs::set_permissions(&nested_read_dir, fs::Permissions::from_mode(0o000))
+                    .expect("Could not set permissions");
+                if fs::read_dir(&nested_read_dir).is_ok() {
+                    return;
+                }
+
+                // Check that updating the links succeeds, and that the nested file is kept
+                fs::remove_file(write_dir.path().join("test_file0"))
+                    .expect("Could not delete file");
+                let result = monitor.update_links();
+                fs::set_permissions(&nested_read_dir, fs::Permissions::from_mode(0o755))
+                    .expect("Could not set permissions");
+                result.expect("Could not update links");
+                assert!(write_dir.path().join("test_file0").is_file());
+                assert!(nested_write_file.is_file());
+
+                // Check that the unreadable directory is reported
+                assert!(monitor.issues.iter().any(|issue| matches!(
+                    issue,
+                    MonitorIssue::ReadFailed { error } if error.path == nested_read_dir
+                )));
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - A tracked file does not fit on the destination disk
+            #[test]
+            fn insufficient_space() {
+                // Generate a file monitor
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+
+                // Get the read and write paths for the test file
+                let filename = "test_file0";
+                let read_path = read_dir.path().join(filename);
+                let write_path = write_dir.path().join(filename);
+
+                // Make the read file larger than the available space on the destination disk
+                let space = disk_space(write_dir.path()).expect("Could not get the disk space");
+                fs::File::options()
+                    .write(true)
+                    .open(&read_path)
+                    .expect("Could not open the read file")
+                    .set_len(space.available + 1024 * 1024 * 1024)
+                    .expect("Could not resize the read file");
+
+                // Update the links
+                monitor.update_links().expect("Unable to update links");
+
+                // Check that the file was not written and the issue was recorded
+                assert!(!write_path.as_path().exists());
+                assert!(monitor.issues.iter().any(|issue| matches!(
+                    issue,
+                    MonitorIssue::InsufficientSpace { file, .. } if file.ends_with(filename)
+                )));
+
+                // Check that the other files were still written
+                assert!(write_dir.path().join("test_file1").is_file());
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - A tracked .mpy file is compiled for a different CircuitPython major version
+            #[test]
+            fn mpy_version_mismatch() {
+                // Generate a file monitor writing to a stand-in CircuitPython 8 board
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                fs::copy(
+                    "tests/assets/boot_out.txt",
+                    write_dir.path().join(crate::board::BOOT_OUT_FILENAME),
+                )
+                .expect("Could not copy boot_out.txt");
+
+                // Create .mpy files compiled for CircuitPython 8 and 9
+                monitor.read_patterns = vec![String::from("test*.mpy")];
+                fs::write(read_dir.path().join("test8.mpy"), b"C\x05\x02\x1f")
+                    .expect("Could not write the CircuitPython 8 file");
+                fs::write(read_dir.path().join("test9.mpy"), b"C\x06\x02\x1f")
+                    .expect("Could not write the CircuitPython 9 file");
+
+                // Update the links
+                monitor.update_links().expect("Unable to update links");
+
+                // Check that only the CircuitPython 9 file was flagged
+                assert_eq!(monitor.issues.len(), 1);
+                let MonitorIssue::MpyVersionMismatch {
+                    file, mpy_version, ..
+                } = &monitor.issues[0]
+                else {
+                    panic!("Unexpected issue recorded");
+                };
+                assert!(file.ends_with("test9.mpy"));
+                assert_eq!(*mpy_version, 6);
+
+                // Check that the same issue is found when checking before starting
+                assert_eq!(monitor.check_mpy_versions(), monitor.issues);
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - A serial port is set for soft rebooting the board after writing files
+            #[test]
+            fn soft_reboot() {
+                // Generate a file monitor using a stand-in serial device
+                let (mut monitor, _read_dir, _write_dir) = get_monitor();
+                let device = tempfile::NamedTempFile::new()
+                    .expect("Could not create stand-in serial device");
+                monitor.serial_port = Some(device.path().to_path_buf());
+
+                // Check that the board is soft rebooted once after writing the files
+                monitor.update_links().expect("Unable to update links");
+                let written = fs::read(device.path()).expect("Could not read serial device");
+                assert_eq!(written, crate::serial::SOFT_REBOOT_SEQUENCE);
+
+                // Check that the board is not soft rebooted again if nothing is written
+                monitor.update_links().expect("Unable to update links");
+                let written = fs::read(device.path()).expect("Could not read serial device");
+                assert_eq!(written, crate::serial::SOFT_REBOOT_SEQUENCE);
+
+                // Check that a missing serial device is recorded as an issue
+                let (mut monitor, _read_dir, _write_dir) = get_monitor();
+                let port = PathBuf::from("/does/not/exist");
+                monitor.serial_port = Some(port.clone());
+                monitor.update_links().expect("Unable to update links");
+                assert!(monitor
+                    .issues
+                    .contains(&MonitorIssue::SoftRebootFailed { port }));
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - File contents are compared using hashes instead of modification times
+            #[test]
+            fn hash_change_detection() {
+                // Generate a file monitor comparing file hashes
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                monitor.change_detection = ChangeDetection::Hash;
+
+                // Write identical contents to a destination file with an older modification time
+                let unchanged_path = write_dir.path().join("test_file0");
+                let contents = fs::read(read_dir.path().join("test_file0"))
+                    .expect("Could not read the first test file");
+                fs::write(&unchanged_path, contents).expect("Could not write the first test file");
+                let old_mtime = FileTime::from_unix_time(0, 0);
+                set_file_mtime(&unchanged_path, old_mtime)
+                    .expect("Could not set file modification time");
+
+                // Write different contents to a destination file with a newer modification time
+                let changed_path = write_dir.path().join("test_file1");
+                fs::write(&changed_path, "changed").expect("Could not write the second test file");
+                set_file_mtime(
+                    &changed_path,
+                    FileTime::from_unix_time(i64::from(u32::MAX), 0),
+                )
+                .expect("Could not set file modification time");
+
+                // Update the links
+                monitor.update_links().expect("Unable to update links");
+
+                // Check that only the file with different contents was rewritten
+                let unchanged_mtime = FileTime::from_last_modification_time(
+                    &fs::metadata(&unchanged_path).expect("Could not get file metadata"),
+                );
+                assert_eq!(unchanged_mtime, old_mtime);
+                let changed =
+                    fs::read_to_string(&changed_path).expect("Could not read the second test file");
+                assert_ne!(changed, "changed");
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - The write location is read-only
+            #[test]
+            fn read_only() {
+                // Generate a file monitor and mark its write directory as read-only
+                let (mut monitor, _read_dir, write_dir) = get_monitor();
+                let mut permissions = fs::metadata(write_dir.path())
+                    .expect("Could not get the write directory metadata")
+                    .permissions();
+                permissions.set_readonly(true);
+                fs::set_permissions(write_dir.path(), permissions.clone())
+                    .expect("Could not mark the write directory as read-only");
+
+                // Check that the file monitor is blocked and does not write any files
+                assert!(monitor.check_read_only());
+                monitor.retry_after = None;
+                monitor.update_links().expect("Unable to update links");
+                assert_eq!(monitor.blocked, Some(BlockedReason::ReadOnly));
+                assert!(!write_dir.path().join("test_file0").exists());
+
+                // Check that the file monitor is unblocked once the write directory is writable
+                #[allow(clippy::permissions_set_readonly_false)]
+                permissions.set_readonly(false);
+                fs::set_permissions(write_dir.path(), permissions)
+                    .expect("Could not mark the write directory as writable");
+                monitor.retry_after = None;
+                monitor.update_links().expect("Unable to update links");
+                assert_eq!(monitor.blocked, None);
+                assert!(write_dir.path().join("test_file0").is_file());
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - Python files are compiled, changing their destination filenames
+            #[test]
+            #[cfg(target_family = "unix")]
+            fn transforms() {
+                // Generate a file monitor compiling Python files using a stand-in compiler
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                monitor.read_patterns = vec![String::from("*.py")];
+                monitor.transforms = vec![
+                    Transform::StripComments,
+                    Transform::compile_python("cp {input} {output}"),
+                ];
+                let read_path = read_dir.path().join("module.py");
+                fs::write(&read_path, "x = 1  # comment\n").expect("Could not write module.py");
+                fs::write(read_dir.path().join("code.py"), "import module\n")
+                    .expect("Could not write code.py");
+
+                // Check that the compiled output is written with the new filename
+                monitor.update_links().expect("Unable to update links");
+                let write_path = write_dir.path().join("module.mpy");
+                let contents = fs::read_to_string(&write_path).expect("Could not read module.mpy");
+                assert_eq!(contents, "x = 1\n");
+                assert!(!write_dir.path().join("module.py").exists());
+                assert!(write_dir.path().join("code.py").is_file());
+
+                // Check that the compiled output is deleted along with the source file
+                fs::remove_file(&read_path).expect("Could not delete module.py");
+                monitor.update_links().expect("Unable to update links");
+                assert!(!write_path.exists());
+
+                // Check that a failing compiler is recorded as an issue
+                monitor.transforms = vec![Transform::compile_python("false")];
+                fs::write(&read_path, "x = 2\n").expect("Could not write module.py");
+                monitor.update_links().expect("Unable to update links");
+                assert!(!write_path.exists());
+                assert!(monitor.issues.contains(&MonitorIssue::TransformFailed {
+                    file: read_path,
+                    error: TransformError::CommandFailed,
+                }));
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - Mapped Python files are compiled based on their destinations, for both the
+            ///   destination filenames and the contents written
+            #[test]
+            #[cfg(target_family = "unix")]
+            fn mapped_transforms() {
+                // Generate a file monitor compiling Python files using a stand-in compiler, with
+                // a library file mapped to the entry point and an entry point mapped into lib
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                let compiled_dir = TempDir::new().expect("Could not create temporary directory");
+                let compiled_path = compiled_dir.path().join("compiled.mpy");
+                fs::write(&compiled_path, "compiled").expect("Could not write compiled.mpy");
+                monitor.read_patterns = vec![String::from("**/*.py")];
+                monitor.transforms = vec![Transform::compile_python(&format!(
+                    "cp {} {{output}}",
+                    compiled_path.display()
+                ))];
+                monitor.mappings = vec![
+                    PathMapping::parse_file("lib_src/helper.py=code.py").unwrap(),
+                    PathMapping::parse_file("apps/x/main.py=lib/x.py").unwrap(),
+                ];
+                for filename in ["lib_src/helper.py", "apps/x/main.py"] {
+                    let path = read_dir.path().join(filename);
+                    fs::create_dir_all(path.parent().unwrap()).expect("Could not create directory");
+                    fs::write(&path, "source").expect("Could not write source file");
+                }
+
+                // Check that the file mapped to the entry point is written as source code
+                monitor.update_links().expect("Unable to update links");
+                let code_path = write_dir.path().join("code.py");
+                assert_eq!(fs::read_to_string(&code_path).unwrap(), "source");
+                assert!(!write_dir.path().join("code.mpy").exists());
+
+                // Check that the entry point mapped into lib is compiled
+                let lib_path = write_dir.path().join("lib/x.mpy");
+                assert_eq!(fs::read_to_string(&lib_path).unwrap(), "compiled");
+                assert!(!write_dir.path().join("lib/x.py").exists());
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - Symlinked files and directories are followed, including a symlink cycle
+            /// - A destination resolves outside the write directory
+            #[test]
+            #[cfg(target_family = "unix")]
+            fn follow_symlinks() {
+                use std::os::unix::fs::symlink;
+
+                // Generate a file monitor following symlinks
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                monitor.read_patterns = vec![String::from("**/*.py")];
+                monitor.follow_symlinks = true;
+
+                // Symlink a shared directory and file into the read directory, with the shared
+                // directory linking back to the read directory
+                let shared_dir = TempDir::new().expect("Could not create the shared directory");
+                let shared_path = shared_dir.path().join("driver.py");
+                fs::write(&shared_path, "driver").expect("Could not write driver.py");
+                symlink(shared_dir.path(), read_dir.path().join("lib"))
+                    .expect("Could not symlink the shared directory");
+                symlink(&shared_path, read_dir.path().join("linked.py"))
+                    .expect("Could not symlink driver.py");
+                symlink(read_dir.path(), shared_dir.path().join("loop"))
+                    .expect("Could not create the symlink cycle");
+
+                // Create a directory in the write directory that links outside of it
+                let outside_dir = TempDir::new().expect("Could not create the outside directory");
+                fs::create_dir(read_dir.path().join("outside"))
+                    .expect("Could not create the outside directory");
+                let outside_path = read_dir.path().join("outside/escape.py");
+                fs::write(&outside_path, "escape").expect("Could not write escape.py");
+                symlink(outside_dir.path(), write_dir.path().join("outside"))
+                    .expect("Could not symlink the outside directory");
+
+                // Update the links
+                monitor.update_links().expect("Unable to update links");
+
+                // Check that the symlinked files were written
+                for path in ["lib/driver.py", "linked.py"] {
+                    let contents = fs::read_to_string(write_dir.path().join(path))
+                        .expect("Could not read the symlinked file");
+                    assert_eq!(contents, "driver");
+                }
+                assert_eq!(monitor.links.len(), 2);
+
+                // Check that the file with a destination outside the write directory was refused
+                assert!(!outside_dir.path().join("escape.py").exists());
+                let file = fs::canonicalize(&outside_path).expect("Could not resolve escape.py");
+                assert!(monitor
+                    .issues
+                    .contains(&MonitorIssue::DestinationOutsideWriteDirectory { file }));
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - Tracked files are removed while using the keep and trash deletion policies
+            #[test]
+            fn deletion_policies() {
+                for deletion_policy in [DeletionPolicy::Keep, DeletionPolicy::Trash] {
+                    // Generate a file monitor using the deletion policy
+                    let (mut monitor, read_dir, write_dir) = get_monitor();
+                    monitor.deletion_policy = deletion_policy;
+
+                    // Write the files, and then delete one of the source files
+                    monitor.update_links().expect("Unable to update links");
+                    let filename = "test_file1";
+                    let write_path = write_dir.path().join(filename);
+                    assert!(write_path.is_file());
+                    fs::remove_file(read_dir.path().join(filename))
+                        .expect("Could not delete the source file");
+
+                    // Update the links
+                    monitor
+                        .update_links()
+                        .expect("Unable to remove file as part of update");
+
+                    // Check that the destination file was kept or moved to the trash directory
+                    let trash_path = write_dir.path().join(TRASH_DIRECTORY_NAME).join(filename);
+                    match deletion_policy {
+                        DeletionPolicy::Keep => {
+                            assert!(write_path.is_file());
+                            assert!(!trash_path.exists());
+                        }
+                        _ => {
+                            assert!(!write_path.exists());
+                            assert!(trash_path.is_file());
+                        }
+                    }
+                    assert_eq!(monitor.links.len(), 3);
+                }
+            }
+
+            /// Tests FileMonitor::update_changed_links(), where:
+            ///
+            /// - Only the changed source files are added, updated and removed
+            #[test]
+            fn update_changed_links() {
+                // Generate a file monitor and write the files
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                monitor.update_links().expect("Unable to update links");
+
+                // Add a source file, and an untracked file only changed without being reported
+                let added_path = read_dir.path().join("test_file4");
+                let unreported_path = read_dir.path().join("test_file5");
+                let ignored_path = read_dir.path().join("other_file");
+                for path in [&added_path, &unreported_path, &ignored_path] {
+                    fs::write(path, "new").expect("Could not write to source file");
+                }
+                monitor
+                    .update_changed_links(&[added_path.clone(), ignored_path.clone()])
+                    .expect("Unable to update changed links");
+                assert!(write_dir.path().join("test_file4").is_file());
+                assert!(!write_dir.path().join("test_file5").exists());
+                assert!(!write_dir.path().join("other_file").exists());
+                assert_eq!(monitor.links.len(), 5);
+
+                // Remove a source file
+                fs::remove_file(&added_path).expect("Could not delete source file");
+                monitor
+                    .update_changed_links(&[added_path])
+                    .expect("Unable to update changed links");
+                assert!(!write_dir.path().join("test_file4").exists());
+                assert_eq!(monitor.links.len(), 4);
+
+                // Check that a changed directory updates all of the tracked files
+                monitor
+                    .update_changed_links(&[read_dir.path().to_path_buf()])
+                    .expect("Unable to update changed links");
+                assert!(write_dir.path().join("test_file5").is_file());
+                assert_eq!(monitor.links.len(), 5);
+            }
+
+            /// Tests FileMonitor::update_links(), where:
+            ///
+            /// - Tracked files are renamed, or replaced by files with the same contents, so their
+            ///   destination files are renamed instead of being written again
+            #[cfg(unix)]
+            #[test]
+            fn renames() {
+                use std::os::unix::fs::MetadataExt;
+
+                // Generate a file monitor comparing file hashes, as copied source files are newer
+                // than their destination files, and write the files with distinct contents
+                let (mut monitor, read_dir, write_dir) = get_monitor();
+                monitor.change_detection = ChangeDetection::Hash;
+                for i in 0..4 {
+                    fs::write(
+                        read_dir.path().join(format!("test_file{i}")),
+                        format!("{i}"),
+                    )
+                    .expect("Could not write to source file");
+                }
+                monitor.update_links().expect("Unable to update links");
+                let get_inode = |filename: &str| {
+                    fs::metadata(write_dir.path().join(filename))
+                        .expect("Could not get destination metadata")
+                        .ino()
+                };
+                let renamed_inode = get_inode("test_file1");
+                let replaced_inode = get_inode("test_file2");
+
+                // Rename one source file, and replace another with a copy under a new name
+                fs::rename(
+                    read_dir.path().join("test_file1"),
+                    read_dir.path().join("test_file_renamed"),
+                )
+                .expect("Could not rename source file");
+                fs::copy(
+                    read_dir.path().join("test_file2"),
+                    read_dir.path().join("test_file_replaced"),
+                )
+                .expect("Could not copy source file");
+                fs::remove_file(read_dir.path().join("test_file2"))
+                    .expect("Could not delete source file");
+                monitor.update_links().expect("Unable to update links");
+
+                // Check that the destination files were renamed rather than written again
+                assert!(!write_dir.path().join("test_file1").exists());
+                assert!(!write_dir.path().join("test_file2").exists());
+                assert_eq!(get_inode("test_file_renamed"), renamed_inode);
+                assert_eq!(get_inode("test_file_replaced"), replaced_inode);
+                let contents = fs::read_to_string(write_dir.path().join("test_file_renamed"))
+                    .expect("Could not read destination file");
+                assert_eq!(contents, "1");
+                assert_eq!(monitor.links.len(), 4);
+            }
+
+            /// Tests FileMonitor::remove_temp_files()
+            #[test]
+            fn remove_temp_files() {
+                // Generate a file monitor
+                let (monitor, _read_dir, write_dir) = get_monitor();
+
+                // Create stray temporary files in the write directory, along with other files
+                let nested_dir = write_dir.path().join("lib");
+                fs::create_dir(&nested_dir).expect("Could not create nested directory");
+                let temp_paths = [
+                    write_dir.path().join(format!(".code.py{TEMP_FILE_SUFFIX}")),
+                    nested_dir.join(format!(".module.py{TEMP_FILE_SUFFIX}")),
+                ];
+                let other_path = write_dir.path().join("code.py");
+                for path in temp_paths.iter().chain([&other_path]) {
+                    fs::write(path, "").expect("Could not create file");
+                }
+
+                // Check that only the temporary files are removed
+                monitor.remove_temp_files();
+                for path in &temp_paths {
+                    assert!(!path.exists());
+                }
+                assert!(other_path.is_file());
             }
 
             /// Tests FileMonitor::update_links(), where:
@@ -669,7 +2986,7 @@ mod tests {
                 let (mut monitor, _read_dir, _write_dir) = get_monitor();
 
                 // Set the read pattern to a bad glob pattern
-                monitor.read_pattern = "text[text".to_string();
+                monitor.read_patterns = vec!["text[text".to_string()];
 
                 // Check that updating the links causes an error
                 let error = monitor
@@ -697,7 +3014,7 @@ mod tests {
                 let table = monitor.to_table_record(true);
 
                 // Calculate the expected table record
-                let read_pattern = monitor.read_pattern;
+                let read_pattern = monitor.read_patterns.join("\n");
                 let write_directory = monitor.write_directory.to_str().unwrap().to_string();
                 let base_directory = monitor.base_directory.to_str().unwrap().to_string();
                 let expected = vec![read_pattern, base_directory, write_directory];
@@ -719,7 +3036,7 @@ mod tests {
                 let table = monitor.to_table_record(false);
 
                 // Calculate the expected table record
-                let read_pattern = monitor.read_pattern;
+                let read_pattern = monitor.read_patterns.join("\n");
                 let current_dir = env::current_dir().expect("Could not get the current directory");
                 let base_directory = diff_paths(&monitor.base_directory, &current_dir)
                     .unwrap()
@@ -764,7 +3081,7 @@ mod tests {
                 let table = monitor.to_table_record(false);
 
                 // Calculate the expected table record
-                let read_pattern = monitor.read_pattern;
+                let read_pattern = monitor.read_patterns.join("\n");
                 let base_directory = String::from(".");
                 let write_directory =
                     diff_paths(&monitor.write_directory, &env::current_dir().unwrap())
@@ -813,7 +3130,7 @@ mod tests {
                 let table = monitor.to_table_record(false);
 
                 // Calculate the expected table record
-                let read_pattern = monitor.read_pattern;
+                let read_pattern = monitor.read_patterns.join("\n");
                 let base_directory =
                     diff_paths(&monitor.base_directory, &env::current_dir().unwrap())
                         .unwrap()
@@ -881,6 +3198,254 @@ mod tests {
             assert_eq!(linkless, monitor);
         }
 
+        mod web_workflow {
+
+            use super::*;
+
+            use crate::test_support::WebWorkflowStandIn;
+            use filetime::{set_file_mtime, FileTime};
+
+            /// Creates a file monitor writing to the "lib" folder of a stand-in board using the
+            /// web workflow, which responds to writes with the given status code
+            fn get_web_monitor(status: u16) -> (FileMonitor, TempDir, WebWorkflowStandIn) {
+                let (monitor, read_dir, _write_dir) = get_monitor();
+                let stand_in = WebWorkflowStandIn::start(move |request| {
+                    if request.method == "DELETE" {
+                        204
+                    } else {
+                        status
+                    }
+                });
+                let web_workflow =
+                    WebWorkflow::new(&stand_in.url(), "secret").expect("Could not parse URL");
+                let monitor = FileMonitor::new_web(
+                    &monitor.read_patterns,
+                    web_workflow,
+                    Path::new("/lib"),
+                    read_dir.path(),
+                );
+                (monitor, read_dir, stand_in)
+            }
+
+            /// Tests FileMonitor::update_links() creating, updating and deleting files on a board
+            /// using the web workflow
+            #[test]
+            fn update_links() {
+                // Generate a file monitor
+                let (mut monitor, read_dir, stand_in) = get_web_monitor(201);
+
+                // Check that the directory and all four files are written
+                monitor.update_links().expect("Unable to update links");
+                let requests = stand_in.requests();
+                assert_eq!(requests.len(), 5);
+                assert_eq!(requests[0].route, "/fs/lib/");
+                assert!(requests[1..].iter().all(|request| request.method == "PUT"));
+                assert!(monitor.issues.is_empty());
+
+                // Check that nothing is written if nothing has changed
+                monitor.update_links().expect("Unable to update links");
+                assert_eq!(stand_in.requests().len(), 5);
+
+                // Check that only a modified file is written again
+                let modified = read_dir.path().join("test_file0");
+                fs::write(&modified, "updated").expect("Could not modify the file");
+                set_file_mtime(&modified, FileTime::from_unix_time(2_000_000_000, 0))
+                    .expect("Could not set the modification time");
+                monitor.update_links().expect("Unable to update links");
+                let requests = stand_in.requests();
+                assert_eq!(requests.len(), 6);
+                assert_eq!(requests[5].route, "/fs/lib/test_file0");
+                assert_eq!(requests[5].body, b"updated");
+
+                // Check that deleting a source file deletes it from the board
+                fs::remove_file(read_dir.path().join("test_file1"))
+                    .expect("Could not delete the file");
+                monitor.update_links().expect("Unable to update links");
+                let requests = stand_in.requests();
+                assert_eq!(requests.len(), 7);
+                assert_eq!(requests[6].method, "DELETE");
+                assert_eq!(requests[6].route, "/fs/lib/test_file1");
+            }
+
+            /// Tests FileMonitor::update_links() when the board rejects the files
+            #[test]
+            fn upload_failed() {
+                // Generate a file monitor for a board that is connected over USB
+                let (mut monitor, _read_dir, stand_in) = get_web_monitor(403);
+
+                // Check that the failure is recorded as an issue without writing further files
+                monitor.update_links().expect("Unable to update links");
+                assert_eq!(stand_in.requests().len(), 1);
+                assert!(matches!(
+                    monitor.issues.as_slice(),
+                    [MonitorIssue::UploadFailed {
+                        error: WebWorkflowError::ReadOnly,
+                        ..
+                    }]
+                ));
+
+                // Check that the board is not retried immediately
+                monitor.update_links().expect("Unable to update links");
+                assert_eq!(stand_in.requests().len(), 1);
+            }
+
+            /// Tests FileMonitor::to_table_record() for a board using the web workflow
+            #[test]
+            fn to_table_record() {
+                let (monitor, _read_dir, stand_in) = get_web_monitor(201);
+                let record = monitor.to_table_record(true);
+                assert_eq!(record[2], format!("{}/lib", stand_in.url()));
+            }
+        }
+
+        mod board_binding {
+
+            use super::*;
+
+            use crate::board::BOOT_OUT_FILENAME;
+
+            /// Creates a file monitor bound to a stand-in board, writing to a "lib" folder on it
+            fn get_bound_monitor() -> (FileMonitor, TempDir, TempDir) {
+                // Generate a file monitor, using its write directory as the board
+                let (mut monitor, read_dir, board_dir) = get_monitor();
+                fs::copy(
+                    "tests/assets/boot_out.txt",
+                    board_dir.path().join(BOOT_OUT_FILENAME),
+                )
+                .expect("Could not copy boot_out.txt");
+
+                // Bind the file monitor to the board
+                let board =
+                    BoardInfo::from_mount(board_dir.path()).expect("Could not read the board");
+                monitor.write_directory = board_dir.path().join("lib");
+                monitor.board = BoardBinding::new(&board, &monitor.write_directory);
+
+                // Return the file monitor and temporary read and board directories
+                (monitor, read_dir, board_dir)
+            }
+
+            /// Tests BoardBinding::new()
+            #[test]
+            fn new() {
+                let (monitor, _read_dir, _board_dir) = get_bound_monitor();
+                let binding = monitor.board.expect("File monitor was not bound");
+                assert_eq!(binding.uid, "C4391B2B0D942955");
+                assert_eq!(binding.path, PathBuf::from("lib"));
+            }
+
+            /// Tests FileMonitor::is_write_target_available()
+            #[test]
+            fn is_write_target_available() {
+                // Check that the bound board is available
+                let (mut monitor, _read_dir, board_dir) = get_bound_monitor();
+                assert!(monitor.is_write_target_available());
+
+                // Check that a different board at the same location is not available
+                let uid = monitor.board.as_ref().unwrap().uid.clone();
+                monitor.board.as_mut().unwrap().uid = String::from("DIFFERENT");
+                assert!(!monitor.is_write_target_available());
+                monitor.board.as_mut().unwrap().uid = uid;
+
+                // Check that the board is not available once it is disconnected
+                fs::remove_file(board_dir.path().join(BOOT_OUT_FILENAME))
+                    .expect("Could not remove boot_out.txt");
+                assert!(!monitor.is_write_target_available());
+
+                // Check that unbound file monitors are always available
+                let (monitor, _read_dir, _write_dir) = get_monitor();
+                assert!(monitor.is_write_target_available());
+            }
+
+            /// Tests FileMonitor::check_write_target_available()
+            #[test]
+            fn check_write_target_available() {
+                // Check that the bound board is available
+                let (mut monitor, _read_dir, board_dir) = get_bound_monitor();
+                let interval = Duration::from_secs(60);
+                assert!(monitor.check_write_target_available(interval));
+
+                // Check that the board is not checked again until the interval has passed
+                fs::remove_file(board_dir.path().join(BOOT_OUT_FILENAME))
+                    .expect("Could not remove boot_out.txt");
+                assert!(monitor.check_write_target_available(interval));
+                assert!(!monitor.check_write_target_available(Duration::ZERO));
+            }
+
+            /// Tests FileMonitor::resolve_board()
+            #[test]
+            fn resolve_board() {
+                // Generate a bound file monitor with links to its board
+                let (mut monitor, _read_dir, board_dir) = get_bound_monitor();
+                monitor.update_links().expect("Could not update links");
+                assert!(!monitor.links.is_empty());
+
+                // Check that resolving against the same mount point keeps the links
+                let board =
+                    BoardInfo::from_mount(board_dir.path()).expect("Could not read the board");
+                monitor.resolve_board(std::slice::from_ref(&board));
+                assert_eq!(monitor.write_directory, board_dir.path().join("lib"));
+                assert!(!monitor.links.is_empty());
+
+                // Check that resolving against a remounted board updates the write directory
+                let remounted = BoardInfo {
+                    mount_point: PathBuf::from("/media/CIRCUITPY1"),
+                    ..board
+                };
+                monitor.resolve_board(&[remounted]);
+                assert_eq!(
+                    monitor.write_directory,
+                    PathBuf::from("/media/CIRCUITPY1/lib")
+                );
+                assert!(monitor.links.is_empty());
+
+                // Check that the write directory is unchanged if the board is not connected
+                monitor.resolve_board(&[]);
+                assert_eq!(
+                    monitor.write_directory,
+                    PathBuf::from("/media/CIRCUITPY1/lib")
+                );
+            }
+
+            /// Tests FileMonitor::resolve_board() for file monitors with a destination on the
+            /// board, which can move to other boards
+            #[test]
+            fn resolve_board_destination() {
+                // Generate bound file monitors with and without a destination
+                let (mut monitor, _read_dir, board_dir) = get_bound_monitor();
+                let (mut fixed_monitor, _fixed_read_dir, _fixed_board_dir) = get_bound_monitor();
+                monitor.destination = Some(PathBuf::from("lib"));
+                monitor.update_links().expect("Could not update links");
+                let board =
+                    BoardInfo::from_mount(board_dir.path()).expect("Could not read the board");
+                let other = BoardInfo {
+                    mount_point: PathBuf::from("/media/OTHER"),
+                    uid: Some(String::from("OTHER")),
+                    ..board.clone()
+                };
+                let third = BoardInfo {
+                    mount_point: PathBuf::from("/media/THIRD"),
+                    uid: Some(String::from("THIRD")),
+                    ..board
+                };
+
+                // Check that the file monitor does not move when multiple other boards are
+                // connected
+                monitor.resolve_board(&[other.clone(), third]);
+                assert_eq!(monitor.write_directory, board_dir.path().join("lib"));
+
+                // Check that the file monitor moves to the only other board connected
+                monitor.resolve_board(std::slice::from_ref(&other));
+                assert_eq!(monitor.write_directory, PathBuf::from("/media/OTHER/lib"));
+                assert_eq!(monitor.board.as_ref().unwrap().uid, "OTHER");
+                assert!(monitor.links.is_empty());
+
+                // Check that file monitors without a destination stay with their board
+                let write_directory = fixed_monitor.write_directory.clone();
+                fixed_monitor.resolve_board(&[other]);
+                assert_eq!(fixed_monitor.write_directory, write_directory);
+            }
+        }
+
         mod partial_eq {
 
             use super::*;
@@ -935,7 +3500,7 @@ mod tests {
                 let mut monitor1 = monitor0.clone();
 
                 // Change the read pattern for the cloned file monitor
-                monitor1.read_pattern = String::from("different");
+                monitor1.read_patterns = vec![String::from("different")];
 
                 // Check that the file monitors are no longer equal
                 assert_ne!(monitor0, monitor1);
@@ -1057,7 +3622,7 @@ mod tests {
                 let mut monitor1 = monitor0.clone();
 
                 // Change the read pattern for the cloned monitor
-                monitor1.read_pattern = String::from("different");
+                monitor1.read_patterns = vec![String::from("different")];
 
                 // Feed the first file monitor into its hasher
                 let mut hasher0 = DefaultHasher::new();
@@ -0,0 +1,125 @@
+// SPDX-FileCopyrightText: 2025 Alec Delaney
+// SPDX-License-Identifier: MIT
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// The file extension used by compiled Python files
+pub const MPY_EXTENSION: &str = "mpy";
+
+/// The magic bytes that start compiled Python files, for CircuitPython and MicroPython
+const MPY_MAGIC_BYTES: [u8; 2] = [b'C', b'M'];
+
+/// Compiled Python file header errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MpyError {
+    /// The file could not be read
+    Unreadable,
+    /// The file does not start with a valid header
+    InvalidHeader,
+}
+
+/// Checks whether the given filepath is a compiled Python file
+pub fn is_mpy_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == MPY_EXTENSION)
+}
+
+/// Read the version byte from the header of a compiled Python file
+pub fn read_mpy_version(path: &Path) -> Result<u8, MpyError> {
+    // Read the magic byte and version byte from the start of the file
+    let mut file = fs::File::open(path).map_err(|_| MpyError::Unreadable)?;
+    let mut header = [0u8; 2];
+    if file.read_exact(&mut header).is_err() {
+        return Err(MpyError::InvalidHeader);
+    }
+
+    // Check the magic byte before returning the version
+    if !MPY_MAGIC_BYTES.contains(&header[0]) {
+        return Err(MpyError::InvalidHeader);
+    }
+    Ok(header[1])
+}
+
+/// Get the compiled Python file version importable by the given CircuitPython major version,
+/// if known
+///
+/// The versions follow the MicroPython release each CircuitPython major version is based on:
+///
+/// - 7.x: MicroPython 1.17, version 5
+/// - 8.x: MicroPython 1.18, version 5
+/// - 9.x: MicroPython 1.21, version 6
+/// - 10.x: MicroPython 1.25, version 6
+///
+/// Other major versions are not known, so their compiled Python files are not checked.
+pub fn mpy_version_for(major_version: u32) -> Option<u8> {
+    match major_version {
+        7 | 8 => Some(5),
+        9 | 10 => Some(6),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use tempfile::NamedTempFile;
+
+    /// Creates a temporary file with the given contents
+    fn create_file(contents: &[u8]) -> NamedTempFile {
+        let file = NamedTempFile::new().expect("Could not create temporary file");
+        fs::write(file.path(), contents).expect("Could not write temporary file");
+        file
+    }
+
+    /// Tests reading the version from compiled Python file headers
+    #[test]
+    fn read_mpy_version() {
+        let circuitpython = create_file(b"C\x05\x02\x1f");
+        let micropython = create_file(b"M\x06\x00\x1f");
+        assert_eq!(super::read_mpy_version(circuitpython.path()), Ok(5));
+        assert_eq!(super::read_mpy_version(micropython.path()), Ok(6));
+    }
+
+    /// Tests reading the version from files that are not valid compiled Python files
+    #[test]
+    fn read_mpy_version_error() {
+        let source = create_file(b"print('hello')");
+        let truncated = create_file(b"C");
+        assert_eq!(
+            super::read_mpy_version(source.path()),
+            Err(MpyError::InvalidHeader)
+        );
+        assert_eq!(
+            super::read_mpy_version(truncated.path()),
+            Err(MpyError::InvalidHeader)
+        );
+        assert_eq!(
+            super::read_mpy_version(Path::new("/does/not/exist.mpy")),
+            Err(MpyError::Unreadable)
+        );
+    }
+
+    /// Tests getting the compiled Python file version for CircuitPython major versions
+    #[test]
+    fn mpy_version_for() {
+        let expected = [
+            (5, None),
+            (6, None),
+            (7, Some(5)),
+            (8, Some(5)),
+            (9, Some(6)),
+            (10, Some(6)),
+            (11, None),
+        ];
+        for (major_version, mpy_version) in expected {
+            assert_eq!(
+                super::mpy_version_for(major_version),
+                mpy_version,
+                "CircuitPython {major_version}.x"
+            );
+        }
+    }
+}
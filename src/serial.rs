@@ -0,0 +1,45 @@
+// SPDX-FileCopyrightText: 2025 Alec Delaney
+// SPDX-License-Identifier: MIT
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// The bytes sent to the REPL to interrupt the running program (Ctrl-C) and then
+/// soft reboot the board (Ctrl-D)
+pub const SOFT_REBOOT_SEQUENCE: [u8; 3] = [0x03, 0x03, 0x04];
+
+/// Soft reboot the CircuitPython board connected via the given serial device path
+///
+/// This forces a clean reload of the code on the board, for boards with autoreload
+/// disabled.
+pub fn soft_reboot(port: &Path) -> io::Result<()> {
+    let mut device = OpenOptions::new().write(true).open(port)?;
+    device.write_all(&SOFT_REBOOT_SEQUENCE)?;
+    device.flush()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    /// Tests sending the soft reboot sequence to a stand-in serial device
+    #[test]
+    fn soft_reboot() {
+        let device = NamedTempFile::new().expect("Could not create stand-in serial device");
+        super::soft_reboot(device.path()).expect("Could not soft reboot");
+        let written = fs::read(device.path()).expect("Could not read stand-in serial device");
+        assert_eq!(written, SOFT_REBOOT_SEQUENCE);
+    }
+
+    /// Tests that soft rebooting fails for a missing serial device
+    #[test]
+    fn soft_reboot_missing() {
+        let result = super::soft_reboot(Path::new("/does/not/exist"));
+        assert!(result.is_err());
+    }
+}
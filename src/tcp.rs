@@ -11,6 +11,8 @@ mod test {
 
     use tempfile::TempDir;
 
+    use crate::monitor::FileMonitor;
+
     use super::*;
 
     /// Helper function for running a function with server running in a separate thread
@@ -26,7 +28,7 @@ mod test {
 
         // Spawn a thread for the server
         let handle = thread::spawn(|| {
-            let _resp = server::run_server(0);
+            let _resp = server::run_server(0, crate::watch::WatchBackend::default());
         });
 
         // Allow the server to start
@@ -58,8 +60,9 @@ mod test {
         let tempdir_path = tempdir.path().to_path_buf();
 
         // Get a closure that will start a file monitor using the temporary directory
-        let start_monitor_func =
-            || client::start_monitor(String::from("test*"), tempdir_path.clone(), tempdir_path);
+        let start_monitor_func = move || {
+            client::start_monitor(FileMonitor::new(&["test*"], &tempdir_path, &tempdir_path))
+        };
 
         // Return the closure and temporary directory
         (start_monitor_func, tempdir)
@@ -93,7 +96,8 @@ mod test {
             let preexisted = crate::test_support::save_app_directory();
 
             // Start the server and wait to fully spin up
-            crate::tcp::server::start_server(0).expect("Could not start server");
+            crate::tcp::server::start_server(0, crate::watch::WatchBackend::default())
+                .expect("Could not start server");
 
             // Check the server is running
             while crate::tcp::client::ping(None).is_err() {}
@@ -125,7 +129,7 @@ mod test {
 
         // Spawn a thread to run the server
         let handle = thread::spawn(|| {
-            let _resp = server::run_server(0);
+            let _resp = server::run_server(0, crate::watch::WatchBackend::default());
         });
 
         // Pause for the delay duration
@@ -537,10 +541,27 @@ mod test {
 
         use std::path::PathBuf;
 
+        use crate::board::DetectionRules;
+        use crate::filetree::get_detection_config_file;
+        use crate::monitor::BoardBinding;
         use crate::workspace::Workspace;
 
         use super::*;
 
+        /// Helper function for allowing only the given directory as a board, standing in for a
+        /// connected board
+        fn use_stand_in_board(mount_point: &Path) {
+            let rules = DetectionRules {
+                mount_points: vec![mount_point.to_path_buf()],
+                ..Default::default()
+            };
+            fs::write(
+                get_detection_config_file(),
+                serde_json::to_string(&rules).unwrap(),
+            )
+            .expect("Could not write the config file");
+        }
+
         /// Tests the successful loading of a workspace
         #[test]
         #[serial_test::serial]
@@ -564,7 +585,7 @@ mod test {
                 fs::copy(&src_filepath, &filepath).expect("Could not copy file contents");
 
                 // Load the workspace
-                client::load_workspace(name)
+                client::load_workspace(name, None)
             };
 
             // Run the closure with a server
@@ -575,6 +596,114 @@ mod test {
             assert_eq!(msg, expected_msg);
         }
 
+        /// Tests loading a workspace onto a selected board when the board the workspace was saved
+        /// with is not connected
+        #[test]
+        #[serial_test::serial]
+        fn select_board_absent() {
+            // Store the workspace name
+            let name = "absentboard";
+
+            // Create a directory that stands in for the selected board
+            let board_dir = TempDir::new().expect("Could not create temporary directory");
+            fs::copy(
+                "tests/assets/boot_out.txt",
+                board_dir.path().join("boot_out.txt"),
+            )
+            .expect("Could not copy boot_out.txt");
+            fs::create_dir(board_dir.path().join("lib")).expect("Could not create directory");
+            let source_dir = TempDir::new().expect("Could not create temporary directory");
+
+            // Get a closure for loading a workspace bound to a board that is not connected onto
+            // the stand-in board, saving the loaded file monitors to check them
+            let mut loaded = None;
+            let load_workspace_func = || {
+                use_stand_in_board(board_dir.path());
+                let mut monitor = FileMonitor::new(
+                    &["*.py"],
+                    &PathBuf::from("/nonexistent/board/lib"),
+                    source_dir.path(),
+                );
+                monitor.board = Some(BoardBinding {
+                    uid: String::from("0123456789ABCDEF"),
+                    path: PathBuf::from("lib"),
+                });
+                Workspace::new("Absent board", &[monitor])
+                    .save_as_name(name, false)
+                    .expect("Could not save the workspace");
+
+                let response =
+                    client::load_workspace(name, Some(&board_dir.path().to_string_lossy()));
+                client::save_workspace("loaded", "Loaded", false)
+                    .expect("Could not save the loaded workspace");
+                loaded = Workspace::from_name("loaded").ok();
+                response
+            };
+
+            // Run the closure with a server
+            let response = with_threaded_server(load_workspace_func);
+
+            // Check that the file monitor was retargeted to the same location on the stand-in
+            // board, and bound to it
+            let msg = response.unwrap();
+            assert!(msg.starts_with(&format!("Started workspace '{name}'\nWriting to ")));
+            let loaded = loaded.expect("Could not load the saved workspace");
+            let monitor = &loaded.monitors[0];
+            assert_eq!(monitor.write_directory, board_dir.path().join("lib"));
+            assert_eq!(
+                monitor.board,
+                Some(BoardBinding {
+                    uid: String::from("C4391B2B0D942955"),
+                    path: PathBuf::from("lib"),
+                })
+            );
+        }
+
+        /// Tests attempting to load a workspace onto a selected board when one of its file
+        /// monitors does not write to a board, leaving the current file monitors running
+        #[test]
+        #[serial_test::serial]
+        fn select_board_unmapped_error() {
+            // Store the workspace name
+            let name = "unmapped";
+
+            // Create a directory that stands in for the selected board
+            let board_dir = TempDir::new().expect("Could not create temporary directory");
+            let (start_monitor_func, tempdir) = get_start_monitor_closure();
+
+            // Get a closure for loading a workspace with a file monitor that does not write to a
+            // board onto the stand-in board, saving the file monitors still running to check them
+            let mut kept = None;
+            let load_workspace_func = || {
+                use_stand_in_board(board_dir.path());
+                start_monitor_func().expect("Could not start file monitor");
+                let monitor = FileMonitor::new(&["*.py"], tempdir.path(), tempdir.path());
+                Workspace::new("Unmapped", &[monitor])
+                    .save_as_name(name, false)
+                    .expect("Could not save the workspace");
+
+                let response =
+                    client::load_workspace(name, Some(&board_dir.path().to_string_lossy()));
+                client::save_workspace("kept", "Kept", false)
+                    .expect("Could not save the running file monitors");
+                kept = Workspace::from_name("kept").ok();
+                response
+            };
+
+            // Run the closure with a server
+            let response = with_threaded_server(load_workspace_func);
+
+            // Check that the error was reported and the file monitor already running was kept
+            let msg = response.unwrap_err();
+            assert!(msg.starts_with(&format!(
+                "Could not map the file monitor writing to {} onto ",
+                tempdir.path().display()
+            )));
+            let kept = kept.expect("Could not load the saved workspace");
+            assert_eq!(kept.monitors.len(), 1);
+            assert_eq!(kept.monitors[0].read_patterns, vec![String::from("test*")]);
+        }
+
         /// Tests attempting to load a workspace when the workspace file is formatted incorrectly
         #[test]
         #[serial_test::serial]
@@ -594,7 +723,7 @@ mod test {
                 fs::File::create_new(&filepath).expect("Could not create new file");
 
                 // Load the workspace
-                client::load_workspace(name)
+                client::load_workspace(name, None)
             };
 
             // Run the closure with a server
@@ -616,7 +745,7 @@ mod test {
             let expected_msg = format!("Workspace '{name}' does not exist");
 
             // Get a closure for loading a workspace when the workspace file is formatted incorrectly
-            let load_workspace_func = || client::load_workspace(name);
+            let load_workspace_func = || client::load_workspace(name, None);
 
             // Run the closure with a server
             let response = with_threaded_server(load_workspace_func);
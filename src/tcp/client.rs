@@ -1,15 +1,16 @@
 // SPDX-FileCopyrightText: 2025 Alec Delaney
 // SPDX-License-Identifier: MIT
 
+use crate::board::{find_all_circuitpy, find_board_for_path, select_circuitpy, BoardInfo};
 use crate::commands::{Request, Response, STOP_RESPONSE};
 use crate::filetree::get_port_dir;
-use crate::monitor::{as_table, FileMonitor};
+use crate::monitor::{as_table, BoardBinding, FileMonitor};
 use crate::workspace::{Workspace, WorkspaceLoadError};
 use serde::Deserialize;
 use std::fs;
 use std::io::prelude::*;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream};
-use std::path::PathBuf;
+use std::path::Path;
 use std::time::Duration;
 
 /// Get the active port
@@ -169,28 +170,65 @@ pub fn stop_server() -> Result<String, String> {
 }
 
 /// Send a start file monitor request to the server
-pub fn start_monitor(
-    read_pattern: String,
-    write_directory: PathBuf,
-    base_directory: PathBuf,
-) -> Result<String, String> {
-    // Prevent the use of symlinks
-    if write_directory.as_path().is_symlink() || base_directory.as_path().is_symlink() {
+///
+/// If the file monitor writes to a CircuitPython board and is not already bound to one,
+/// it is bound to that board so it can follow the board if it is remounted elsewhere.
+pub fn start_monitor(mut monitor: FileMonitor) -> Result<String, String> {
+    // Prevent the use of symlinks, unless the file monitor follows them
+    if !monitor.follow_symlinks
+        && (monitor.write_directory.as_path().is_symlink()
+            || monitor.base_directory.as_path().is_symlink())
+    {
         return Err(String::from("ERROR: Symlinks are not allowed"));
     }
 
+    // Store the password for boards using the web workflow, as it is not sent to the server
+    if let Some(web_workflow) = &monitor.web_workflow {
+        if !web_workflow.password.is_empty() {
+            web_workflow.store_password().map_err(|error| {
+                format!("ERROR: Could not store the web workflow password: {error}")
+            })?;
+        }
+    }
+
+    // Get the location being written to, for boards using the web workflow
+    let web_location = monitor
+        .web_workflow
+        .as_ref()
+        .map(|web_workflow| web_workflow.display_url(&monitor.write_directory));
+
+    // Get the CircuitPython board being written to, if any, and bind to it
+    let board = match web_location {
+        Some(_) => None,
+        None => find_board_for_path(&monitor.write_directory).map_err(|error| error.to_string())?,
+    };
+    if monitor.board.is_none() {
+        monitor.board = board
+            .as_ref()
+            .and_then(|board| BoardBinding::new(board, &monitor.write_directory));
+    }
+
     // Communicate with the server
-    match communicate(
+    let mut msg = match communicate(
         None,
         Request::StartLink {
-            read_pattern,
-            write_directory,
-            base_directory,
+            monitor: Box::new(monitor),
         },
     ) {
-        Ok(Response::Message { msg }) => Ok(msg),
-        _ => Err(String::from("ERROR: Could not start link")),
+        Ok(Response::Message { msg }) => msg,
+        _ => return Err(String::from("ERROR: Could not start link")),
+    };
+
+    // Report the board being written to, so it is clear which one was targeted
+    if let Some(board) = board {
+        msg.push_str(&format!("\nWriting to {board}"));
+    }
+    if let Some(web_location) = web_location {
+        msg.push_str(&format!(
+            "\nWriting to {web_location} over the web workflow"
+        ));
     }
+    Ok(msg)
 }
 
 /// Send a stop file monitor request to the server
@@ -221,6 +259,49 @@ pub fn view_monitor(number: usize, absolute: bool) -> Result<String, String> {
     let monitor_list = get_monitor_list(number)?;
 
     let table = as_table(&monitor_list, number, absolute);
+    let mut text = table.to_string();
+
+    // Add the CircuitPython boards being written to by the file monitors, if any, along with
+    // any issues and errors encountered by the file monitors
+    for (index, monitor) in monitor_list.iter().enumerate() {
+        let record_number = if number == 0 { index + 1 } else { number };
+        if monitor.web_workflow.is_none() {
+            if let Ok(Some(board)) = find_board_for_path(&monitor.write_directory) {
+                text.push_str(&format!("\nLink {record_number} writes to {board}"));
+            }
+        }
+        for mapping in &monitor.mappings {
+            text.push_str(&format!("\nLink {record_number} maps {mapping}"));
+        }
+        if let Some(reason) = &monitor.blocked {
+            text.push_str(&format!("\nLink {record_number} is blocked: {reason}"));
+        }
+        for issue in &monitor.issues {
+            text.push_str(&format!("\nLink {record_number}: {issue}"));
+        }
+        if let Some(error) = &monitor.error {
+            text.push_str(&format!("\nLink {record_number}: {error}"));
+        }
+        for error in monitor.link_errors() {
+            text.push_str(&format!("\nLink {record_number}: {error}"));
+        }
+    }
+
+    Ok(text)
+}
+
+/// View the connected CircuitPython boards, along with the file monitors writing to them
+pub fn view_boards() -> Result<String, String> {
+    // Find the connected boards
+    let boards = find_all_circuitpy().map_err(|error| error.to_string())?;
+    if boards.is_empty() {
+        return Ok(String::from("No CircuitPython boards are connected"));
+    }
+
+    // Get the file monitors from the server, if it is running
+    let monitor_list = get_monitor_list(0).unwrap_or_default();
+
+    let table = crate::board::as_table(&boards, &monitor_list);
     Ok(table.to_string())
 }
 
@@ -261,13 +342,29 @@ pub fn set_workspace_name(name: &str) -> Result<String, String> {
     }
 }
 
-/// Load the given workspace
-pub fn load_workspace(name: &str) -> Result<String, String> {
-    // Stop current file monitors
-    if stop_monitor(0).is_err() {
+/// Load the given workspace, optionally writing to the selected CircuitPython board
+///
+/// File monitors bound to a CircuitPython board are first pointed at wherever that board
+/// is currently mounted, or at the only connected board if they have a destination on the
+/// board and their board is not connected.  If a board is selected, every file monitor is
+/// then retargeted to the same location on the selected board, using its destination or board
+/// binding, or else the board its write directory is on.  The current file monitors are only
+/// stopped once all of the file monitors from the workspace are ready to start.
+pub fn load_workspace(name: &str, board: Option<&str>) -> Result<String, String> {
+    // Check that the server is running before loading anything
+    if get_port() == 0 {
         return Err(String::from("ERROR: Could not load the workspace"));
     }
 
+    // Find the selected board, if requested
+    let selected_board = match board {
+        Some(selector) => match select_circuitpy(Some(selector)) {
+            Ok(selected_board) => Some(selected_board),
+            Err(error) => return Err(error.to_string()),
+        },
+        None => None,
+    };
+
     // Load the workspace from the name
     let workspace = match Workspace::from_name(name) {
         Ok(workspace) => workspace,
@@ -279,21 +376,73 @@ pub fn load_workspace(name: &str) -> Result<String, String> {
         }
     };
 
-    // Start the file monitors from the workspace
-    for file_monitor in workspace.monitors {
-        start_monitor(
-            file_monitor.read_pattern,
-            file_monitor.write_directory,
-            file_monitor.base_directory,
-        )
-        .expect("Could not start all file monitors");
+    // Point the file monitors from the workspace at their boards, keeping track of the boards
+    // written to
+    let connected_boards = find_all_circuitpy().map_err(|error| error.to_string())?;
+    let mut boards: Vec<BoardInfo> = Vec::new();
+    let mut file_monitors = Vec::new();
+    for mut file_monitor in workspace.monitors {
+        // Find where the bound board is currently mounted, if it is connected
+        file_monitor.resolve_board(&connected_boards);
+
+        let board = match &selected_board {
+            // Retarget the file monitor to the selected board, keeping its location on the board
+            Some(selected_board) => {
+                let board_path = match file_monitor.board_path() {
+                    Some(board_path) => Some(board_path.to_path_buf()),
+                    None => find_board_for_path(&file_monitor.write_directory)
+                        .map_err(|error| error.to_string())?
+                        .and_then(|board| {
+                            file_monitor
+                                .write_directory
+                                .strip_prefix(&board.mount_point)
+                                .ok()
+                                .map(Path::to_path_buf)
+                        }),
+                };
+                let Some(board_path) = board_path else {
+                    return Err(format!(
+                        "Could not map the file monitor writing to {} onto {selected_board}",
+                        file_monitor.write_directory.display()
+                    ));
+                };
+                file_monitor.set_board(selected_board, &board_path);
+                Some(selected_board.clone())
+            }
+            None => find_board_for_path(&file_monitor.write_directory)
+                .map_err(|error| error.to_string())?,
+        };
+        if let Some(board) = board {
+            if !boards.contains(&board) {
+                boards.push(board);
+            }
+        }
+        file_monitors.push(file_monitor);
+    }
+
+    // Stop current file monitors
+    if stop_monitor(0).is_err() {
+        return Err(String::from("ERROR: Could not load the workspace"));
+    }
+
+    // Start the file monitors from the workspace, stopping them all again if any cannot be
+    // started so that the workspace is not partially loaded
+    for file_monitor in file_monitors {
+        if let Err(error) = start_monitor(file_monitor) {
+            let _ = stop_monitor(0);
+            return Err(format!("Could not start the workspace '{name}': {error}"));
+        }
     }
 
     // Set the workspace name for the server
-    set_workspace_name(name).expect("Could not set the name for the workspace");
+    set_workspace_name(name)?;
 
-    // Retutnr that the workspace was successfully started
-    Ok(format!("Started workspace '{name}'"))
+    // Return that the workspace was successfully started, along with the boards written to
+    let mut msg = format!("Started workspace '{name}'");
+    for board in boards {
+        msg.push_str(&format!("\nWriting to {board}"));
+    }
+    Ok(msg)
 }
 
 /// View the current workspace
@@ -316,6 +465,8 @@ pub fn get_current_workspace() -> Result<String, String> {
 #[cfg(test)]
 mod test {
 
+    use std::path::PathBuf;
+
     use super::*;
 
     mod port_files {
@@ -439,7 +590,8 @@ mod test {
             assert!(symbolic.as_path().is_symlink());
 
             // Attempt to start the monitor with symlinks
-            let error = start_monitor(String::from("test*"), symbolic.clone(), symbolic.clone())
+            let monitor = FileMonitor::new(&["test*"], &symbolic, &symbolic);
+            let error = start_monitor(monitor)
                 .expect_err("Successfully started file monitor when it should have been prevented");
 
             // Remove the symlink
@@ -460,11 +612,11 @@ mod test {
             let resp_msg = "ERROR: Could not start link";
 
             // Get the response of the command
-            let response = start_monitor(
-                String::from("test"),
-                PathBuf::from("test"),
-                PathBuf::from("test"),
-            );
+            let response = start_monitor(FileMonitor::new(
+                &["test"],
+                &PathBuf::from("test"),
+                &PathBuf::from("test"),
+            ));
 
             // Restore the previous application directory if it existed
             crate::test_support::restore_app_directory(preexisted);
@@ -570,7 +722,7 @@ mod test {
         let resp_msg = "ERROR: Could not load the workspace";
 
         // Get the response of the command
-        let response = load_workspace("doesnotexist");
+        let response = load_workspace("doesnotexist", None);
 
         // Restore the previous application directory if it existed
         crate::test_support::restore_app_directory(preexisted);
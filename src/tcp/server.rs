@@ -1,9 +1,11 @@
 // SPDX-FileCopyrightText: 2025 Alec Delaney
 // SPDX-License-Identifier: MIT
 
+use crate::board::find_all_circuitpy;
 use crate::commands::{Request, Response, STOP_RESPONSE};
 use crate::filetree::get_port_dir;
 use crate::monitor::FileMonitor;
+use crate::watch::{SourceWatcher, WatchBackend};
 use serde::Deserialize;
 use std::fs;
 use std::io::prelude::*;
@@ -12,16 +14,129 @@ use std::ops::Index;
 use std::process::Command;
 use std::str::FromStr;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[cfg(target_family = "unix")]
 use std::process::Stdio;
 
-/// State of the server, consisting of the file monitors and the current
-/// workspace name, if any
+/// The minimum time between scans for remounted CircuitPython boards
+const BOARD_SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The longest time to wait for source file changes before checking for connections again
+const CONNECTION_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The time to pause in between re-scans of the source files when polling them
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The minimum time between attempts to watch file monitors that could not be watched, such as
+/// when their directories do not exist yet
+const WATCH_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The time between full re-scans of the source files of file monitors when watching for
+/// changes, in case any changes were missed
+const FULL_RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// State of the server, consisting of the file monitors, the current
+/// workspace name, if any, the time of the last scan for boards, and
+/// when the file monitors were last watched along with whether each of
+/// them is, unless they have changed since
 struct ServerState {
     monitors: Vec<FileMonitor>,
     workspace_name: String,
+    last_board_scan: Instant,
+    watched: Option<(Instant, Vec<bool>)>,
+}
+
+/// Re-resolve the write directories of file monitors whose boards are not
+/// currently available, in case they have been remounted elsewhere, and
+/// remove any temporary files left on boards that have reappeared, fully
+/// updating them as changes may have been missed in the meantime
+fn resolve_unavailable_boards(state: &mut ServerState) {
+    // Only scan for boards periodically, and only if necessary
+    if state.last_board_scan.elapsed() < BOARD_SCAN_INTERVAL {
+        return;
+    }
+    state.last_board_scan = Instant::now();
+    if state
+        .monitors
+        .iter_mut()
+        .all(|monitor| monitor.check_write_target_available(BOARD_SCAN_INTERVAL))
+    {
+        return;
+    }
+
+    // Point the file monitors at wherever their boards are now mounted
+    let Ok(boards) = find_all_circuitpy() else {
+        return;
+    };
+    for monitor in &mut state.monitors {
+        if !monitor.check_write_target_available(BOARD_SCAN_INTERVAL) {
+            monitor.resolve_board(&boards);
+            if monitor.check_write_target_available(Duration::ZERO) {
+                monitor.remove_temp_files();
+                monitor.request_full_update();
+            }
+        }
+    }
+}
+
+/// Update the file links of the file monitors, only updating the changed source files when
+/// watching for changes, and removing broken file monitors
+fn update_monitors(state: &mut ServerState, watcher: Option<&mut SourceWatcher>) {
+    // Get the source files changed since the last update, and which file monitors are being
+    // watched, if watching for changes.  The file monitors are only watched again once they
+    // change, or periodically if any of them could not be watched.
+    if state.watched.as_ref().is_some_and(|(last_watch, watched)| {
+        watched.contains(&false) && last_watch.elapsed() >= WATCH_RETRY_INTERVAL
+    }) {
+        state.watched = None;
+    }
+    let (changes, watched) = match watcher {
+        Some(watcher) => {
+            let (_, watched) = state
+                .watched
+                .get_or_insert_with(|| (Instant::now(), watcher.watch_monitors(&state.monitors)));
+            (Some(watcher.take_changes()), watched.clone())
+        }
+        None => (None, Vec::new()),
+    };
+
+    let mut has_broken_monitors = false;
+    for (index, monitor) in state.monitors.iter_mut().enumerate() {
+        // Skip file monitors whose boards are disconnected for now, only checking the boards
+        // periodically
+        if !monitor.check_write_target_available(BOARD_SCAN_INTERVAL) {
+            continue;
+        }
+
+        // Fully update file monitors that are not being watched or are due a full update, and
+        // otherwise only update the changed source files, if any
+        let is_watched = watched.get(index).copied().unwrap_or(false);
+        let changed = match &changes {
+            Some(changes) if is_watched && !monitor.is_full_update_due(FULL_RESCAN_INTERVAL) => {
+                changes.within(&monitor.get_watch_roots())
+            }
+            _ => None,
+        };
+        let result = match changed {
+            Some(changed) if changed.is_empty() => continue,
+            Some(changed) => monitor.update_changed_links(&changed),
+            None => monitor.update_links(),
+        };
+
+        // Keep the error for reporting to clients, and keep updating the other file monitors
+        monitor.error = result.err();
+        has_broken_monitors |= monitor.error.is_some();
+    }
+    if has_broken_monitors {
+        // Keep file monitors bound to boards, as their boards may reappear
+        state.monitors.retain(|monitor| {
+            monitor.board.is_some()
+                || monitor.web_workflow.is_some()
+                || monitor.write_directory_exists()
+        });
+        state.watched = None;
+    }
 }
 
 /// Checks to see if server is already running
@@ -31,12 +146,13 @@ pub fn is_server_running() -> bool {
 
 /// Starts the server in a seperate process by using `circpush run`
 #[cfg(target_family = "unix")]
-pub fn start_server(port: u16) -> Result<String, String> {
+pub fn start_server(port: u16, backend: WatchBackend) -> Result<String, String> {
     let _daemon = Command::new("circpush")
         .arg("server")
         .arg("run")
         .arg("--port")
         .arg(port.to_string())
+        .args(watcher_args(backend))
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn();
@@ -45,7 +161,7 @@ pub fn start_server(port: u16) -> Result<String, String> {
 
 /// Starts the server in a seperate process by using `circpush run`
 #[cfg(target_family = "windows")]
-pub fn start_server(port: u16) -> Result<String, String> {
+pub fn start_server(port: u16, backend: WatchBackend) -> Result<String, String> {
     use std::os::windows::process::CommandExt;
     use windows_sys::Win32::System::Threading::{CREATE_NEW_PROCESS_GROUP, DETACHED_PROCESS};
     let _daemon = Command::new("circpush")
@@ -53,11 +169,20 @@ pub fn start_server(port: u16) -> Result<String, String> {
         .arg("run")
         .arg("--port")
         .arg(port.to_string())
+        .args(watcher_args(backend))
         .creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP)
         .spawn();
     Ok(String::from("Starting server"))
 }
 
+/// Gets the arguments for running the server with the given watch backend
+fn watcher_args(backend: WatchBackend) -> Vec<&'static str> {
+    match backend {
+        WatchBackend::Notify => Vec::new(),
+        WatchBackend::Poll => vec!["--watcher", "poll"],
+    }
+}
+
 /// Binds to the associated port on localhost as non-blocking
 fn bind_socket(port: u16) -> Result<TcpListener, String> {
     // Get the connection information
@@ -103,23 +228,36 @@ fn handle_connection(mut stream: TcpStream, state: &mut ServerState) -> bool {
         Request::Shutdown => Response::Message {
             msg: String::from_str(STOP_RESPONSE).unwrap(),
         },
-        Request::StartLink {
-            read_pattern,
-            write_directory,
-            base_directory,
-        } => {
-            // Create a new FileMonitor
-            let new_monitor = FileMonitor::new(read_pattern, write_directory, base_directory);
+        Request::StartLink { monitor } => {
+            // Create a new FileMonitor, loading the password for boards using the web workflow
+            // and removing any temporary files left by previous writes
+            let mut new_monitor = monitor.clone_linkless();
+            if let Some(web_workflow) = &mut new_monitor.web_workflow {
+                web_workflow.load_password();
+            }
+            new_monitor.remove_temp_files();
+
+            // Check for compiled Python files the board will not be able to import, and whether
+            // the write location is read-only
+            let mpy_issues = new_monitor.check_mpy_versions();
+            let is_blocked = new_monitor.check_read_only();
 
             // Push the new FileMonitor to the lists
             monitors.push(new_monitor);
             *workspace_name = String::from("");
 
-            // Get the new link number and send it with the response
+            // Get the new link number and send it with the response, along with any warnings
             let new_link_number = monitors.len();
-            Response::Message {
-                msg: format!("Link {new_link_number} started!"),
+            let mut msg = format!("Link {new_link_number} started!");
+            for issue in mpy_issues {
+                msg.push_str(&format!("\nWarning: {issue}"));
+            }
+            if is_blocked {
+                msg.push_str(&format!(
+                    "\nWarning: Link {new_link_number} is blocked, as the write location is read-only"
+                ));
             }
+            Response::Message { msg }
         }
         Request::StopLink { number } => {
             // If the link number is 0, stop all monitors
@@ -201,18 +339,25 @@ fn handle_connection(mut stream: TcpStream, state: &mut ServerState) -> bool {
     !matches!(&request, Request::Shutdown)
 }
 
-/// Run the server loop
-pub fn run_server(port: u16) -> Result<String, String> {
+/// Run the server loop, watching for changes to source files using the given backend
+///
+/// If file change notifications are not available, source files are polled instead.
+pub fn run_server(port: u16, backend: WatchBackend) -> Result<String, String> {
     // Get the TCP listener
     let listener = bind_socket(port)?;
 
-    // Get the duration to pause  in between checking for connections
-    let sleep_duration = Duration::from_millis(10);
+    // Watch for changes to source files if requested
+    let mut watcher = match backend {
+        WatchBackend::Notify => SourceWatcher::new().ok(),
+        WatchBackend::Poll => None,
+    };
 
     // Create the initial list for FileMonitors (empty)
     let mut state = ServerState {
         monitors: Vec::new(),
         workspace_name: String::new(),
+        last_board_scan: Instant::now(),
+        watched: None,
     };
 
     // Handle incoming connections
@@ -221,27 +366,24 @@ pub fn run_server(port: u16) -> Result<String, String> {
             // Incoming connection received
             Ok(stream) => {
                 let keep_running = handle_connection(stream, &mut state);
+                state.watched = None;
                 if !keep_running {
                     break;
                 }
             }
             // No connection received before non-blocking timeout
             _ => {
-                let mut has_broken_monitors = false;
-                for monitor in &mut state.monitors {
-                    if monitor.update_links().is_err() {
-                        has_broken_monitors = true;
-                        break;
-                    }
-                }
-                if has_broken_monitors {
-                    state
-                        .monitors
-                        .retain(|monitor| monitor.write_directory_exists());
+                resolve_unavailable_boards(&mut state);
+                update_monitors(&mut state, watcher.as_mut());
+
+                // Wait for source file changes before checking for connections again, or pause
+                // before polling the source files again
+                match watcher.as_mut() {
+                    Some(watcher) => watcher.wait_for_changes(CONNECTION_CHECK_INTERVAL),
+                    None => sleep(POLL_INTERVAL),
                 }
             }
         }
-        sleep(sleep_duration); // TODO: Remove later?
     }
     Ok(String::from("Server process ended"))
 }
@@ -260,14 +402,14 @@ mod test {
         let preexisted = crate::test_support::save_app_directory();
 
         // Start a server
-        start_server(0).expect("Could not start server");
+        start_server(0, WatchBackend::default()).expect("Could not start server");
 
         // Get the port used by the server
         while crate::tcp::client::ping(None).is_err() {}
         let port = crate::tcp::client::get_port();
 
         // Attempt to run the server on the same port
-        let response = crate::tcp::server::run_server(port);
+        let response = crate::tcp::server::run_server(port, WatchBackend::default());
 
         // Stop the server
         crate::tcp::client::stop_server().expect("Could not stop the server");
@@ -0,0 +1,493 @@
+// SPDX-FileCopyrightText: 2025 Alec Delaney
+// SPDX-License-Identifier: MIT
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The file extension of Python source files
+pub const PYTHON_EXTENSION: &str = "py";
+
+/// The files CircuitPython runs as Python source files from the root of the board, which
+/// cannot be compiled
+pub const ENTRY_POINT_FILENAMES: [&str; 4] = ["boot.py", "code.py", "main.py", "safemode.py"];
+
+/// The placeholder for the input filepath in compiler commands
+pub const INPUT_PLACEHOLDER: &str = "{input}";
+
+/// The placeholder for the output filepath in compiler commands
+pub const OUTPUT_PLACEHOLDER: &str = "{output}";
+
+/// A counter used to create unique directories for compiler commands
+static COMPILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Transform errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransformError {
+    /// The compiler command is empty
+    NoCommand,
+    /// The compiler command could not be run
+    CommandNotRun,
+    /// The compiler command exited unsuccessfully
+    CommandFailed,
+    /// The output of the compiler command could not be read
+    NoOutput,
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransformError::NoCommand => write!(f, "no compiler command was given"),
+            TransformError::CommandNotRun => write!(f, "the compiler command could not be run"),
+            TransformError::CommandFailed => write!(f, "the compiler command failed"),
+            TransformError::NoOutput => write!(f, "the compiler command did not create a file"),
+        }
+    }
+}
+
+/// A transformation applied to the contents of source files before they are written to
+/// their destinations
+///
+/// These can be serialized via JSON so they are saved along with the file monitors in
+/// workspaces.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(tag = "type")]
+pub enum Transform {
+    /// Convert CRLF line endings to LF in text files
+    NormalizeLineEndings,
+    /// Strip comments and docstrings from Python source files, keeping the line numbers
+    StripComments,
+    /// Compile files with the input extension using an external command, such as `mpy-cross`,
+    /// writing the output with the output extension instead
+    ///
+    /// The command is split on whitespace, with the input and output placeholders replaced
+    /// by the paths of the file to compile and the file to create.
+    Compile {
+        command: String,
+        input_extension: String,
+        output_extension: String,
+    },
+}
+
+impl Transform {
+    /// Creates a transform compiling Python source files to .mpy files using the given command
+    pub fn compile_python(command: &str) -> Self {
+        Transform::Compile {
+            command: command.to_string(),
+            input_extension: PYTHON_EXTENSION.to_string(),
+            output_extension: crate::mpy::MPY_EXTENSION.to_string(),
+        }
+    }
+
+    /// Checks whether the transform applies to the given filepath, relative to the write
+    /// directory
+    ///
+    /// Only files directly in the write directory are treated as entry points.
+    pub fn applies_to(&self, path: &Path) -> bool {
+        let has_extension = |extension: &str| path.extension().is_some_and(|ext| ext == extension);
+        match self {
+            Transform::NormalizeLineEndings => true,
+            Transform::StripComments => has_extension(PYTHON_EXTENSION),
+            Transform::Compile {
+                input_extension, ..
+            } => {
+                let is_entry_point = input_extension == PYTHON_EXTENSION
+                    && ENTRY_POINT_FILENAMES
+                        .iter()
+                        .any(|entry| path == Path::new(entry));
+                has_extension(input_extension) && !is_entry_point
+            }
+        }
+    }
+
+    /// Get the filepath of the output of the transform for the given filepath
+    pub fn output_path(&self, path: &Path) -> PathBuf {
+        match self {
+            Transform::Compile {
+                output_extension, ..
+            } if self.applies_to(path) => path.with_extension(output_extension),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Transforms the given contents of the file at the given filepath
+    pub fn apply(&self, path: &Path, contents: Vec<u8>) -> Result<Vec<u8>, TransformError> {
+        if !self.applies_to(path) {
+            return Ok(contents);
+        }
+        match self {
+            Transform::NormalizeLineEndings => Ok(normalize_line_endings(contents)),
+            Transform::StripComments => match String::from_utf8(contents) {
+                Ok(text) => Ok(strip_python_comments(&text).into_bytes()),
+                Err(error) => Ok(error.into_bytes()),
+            },
+            Transform::Compile {
+                command,
+                output_extension,
+                ..
+            } => compile(command, path, output_extension, &contents),
+        }
+    }
+}
+
+/// Checks whether any of the given transforms apply to the given filepath, in order
+pub fn any_applies(transforms: &[Transform], path: &Path) -> bool {
+    let mut path = path.to_path_buf();
+    for transform in transforms {
+        if transform.applies_to(&path) {
+            return true;
+        }
+        path = transform.output_path(&path);
+    }
+    false
+}
+
+/// Get the filepath of the output of the given transforms, in order, for the given filepath
+pub fn output_path(transforms: &[Transform], path: &Path) -> PathBuf {
+    transforms
+        .iter()
+        .fold(path.to_path_buf(), |path, transform| {
+            transform.output_path(&path)
+        })
+}
+
+/// Apply the given transforms, in order, to the given contents of the file at the given filepath
+pub fn apply_all(
+    transforms: &[Transform],
+    path: &Path,
+    contents: Vec<u8>,
+) -> Result<Vec<u8>, TransformError> {
+    let mut path = path.to_path_buf();
+    let mut contents = contents;
+    for transform in transforms {
+        contents = transform.apply(&path, contents)?;
+        path = transform.output_path(&path);
+    }
+    Ok(contents)
+}
+
+/// Convert CRLF line endings to LF, leaving contents that are not text unchanged
+fn normalize_line_endings(contents: Vec<u8>) -> Vec<u8> {
+    match String::from_utf8(contents) {
+        Ok(text) => text.replace("\r\n", "\n").into_bytes(),
+        Err(error) => error.into_bytes(),
+    }
+}
+
+/// Get the index just past the end of the string literal with its opening quote at the given
+/// index, or the end of the line for unterminated string literals
+fn find_string_end(chars: &[char], start: usize) -> usize {
+    let quote = chars[start];
+    let is_triple = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let mut index = if is_triple { start + 3 } else { start + 1 };
+    while index < chars.len() {
+        match chars[index] {
+            '\\' => index += 2,
+            '\n' if !is_triple => return index,
+            character if character == quote => {
+                if !is_triple {
+                    return index + 1;
+                }
+                if chars.get(index + 1) == Some(&quote) && chars.get(index + 2) == Some(&quote) {
+                    return index + 3;
+                }
+                index += 1;
+            }
+            _ => index += 1,
+        }
+    }
+    chars.len()
+}
+
+/// Checks whether only whitespace and comments follow the given index on its line
+fn is_rest_of_line_empty(chars: &[char], start: usize) -> bool {
+    for &character in &chars[start..] {
+        match character {
+            ' ' | '\t' => continue,
+            '#' | '\r' | '\n' => return true,
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Strip the comments and docstrings from the given Python source code
+///
+/// Comments are removed along with any trailing whitespace, and strings used as statements
+/// (such as docstrings) are replaced with `pass`, so that every line stays on the same line
+/// number and tracebacks still point to the right lines.
+pub fn strip_python_comments(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut output = String::with_capacity(source.len());
+    let mut bracket_depth = 0usize;
+    let mut at_statement_start = true;
+    let mut index = 0;
+
+    while index < chars.len() {
+        let character = chars[index];
+        match character {
+            // Remove comments up to the end of the line
+            '#' => {
+                while index < chars.len() && !matches!(chars[index], '\r' | '\n') {
+                    index += 1;
+                }
+            }
+            // Remove trailing whitespace at the end of each line, which starts a new statement
+            // unless inside brackets
+            '\r' | '\n' => {
+                let trimmed_length = output.trim_end_matches([' ', '\t']).len();
+                output.truncate(trimmed_length);
+                output.push(character);
+                if bracket_depth == 0 {
+                    at_statement_start = true;
+                }
+                index += 1;
+            }
+            // Keep explicit line continuations, which do not start a new statement
+            '\\' => {
+                output.push(character);
+                if let Some(&next) = chars.get(index + 1) {
+                    output.push(next);
+                }
+                at_statement_start = false;
+                index += 2;
+            }
+            ' ' | '\t' => {
+                output.push(character);
+                index += 1;
+            }
+            '(' | '[' | '{' => {
+                bracket_depth += 1;
+                output.push(character);
+                at_statement_start = false;
+                index += 1;
+            }
+            ')' | ']' | '}' => {
+                bracket_depth = bracket_depth.saturating_sub(1);
+                output.push(character);
+                at_statement_start = false;
+                index += 1;
+            }
+            // Keep names and numbers whole, along with any string literals they prefix
+            character if character.is_alphanumeric() || character == '_' => {
+                let start = index;
+                while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_')
+                {
+                    index += 1;
+                }
+                let word: String = chars[start..index].iter().collect();
+                let is_prefix = matches!(
+                    word.to_lowercase().as_str(),
+                    "r" | "u" | "b" | "f" | "br" | "rb" | "fr" | "rf"
+                );
+                if is_prefix && matches!(chars.get(index), Some('"' | '\'')) {
+                    index = find_string_end(&chars, index);
+                }
+                output.extend(&chars[start..index]);
+                at_statement_start = false;
+            }
+            // Replace strings used as statements with pass, keeping their line breaks
+            '"' | '\'' => {
+                let end = find_string_end(&chars, index);
+                if at_statement_start && bracket_depth == 0 && is_rest_of_line_empty(&chars, end) {
+                    output.push_str("pass");
+                    output.extend(chars[index..end].iter().filter(|&&c| c == '\n'));
+                } else {
+                    output.extend(&chars[index..end]);
+                }
+                at_statement_start = false;
+                index = end;
+            }
+            _ => {
+                output.push(character);
+                at_statement_start = false;
+                index += 1;
+            }
+        }
+    }
+
+    output
+}
+
+/// Compile the given contents of the file at the given filepath using the given command,
+/// returning the contents of the output file
+///
+/// The contents are written to a file with the same name in a temporary directory, so the
+/// compiler sees the original filename.
+fn compile(
+    command: &str,
+    path: &Path,
+    output_extension: &str,
+    contents: &[u8],
+) -> Result<Vec<u8>, TransformError> {
+    // Create a temporary directory for the input and output files
+    let directory = env::temp_dir().join(format!(
+        "circpush-compile-{}-{}",
+        process::id(),
+        COMPILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let filename = path.file_name().unwrap_or_default();
+    let input_path = directory.join(filename);
+    let output_path = input_path.with_extension(output_extension);
+    if fs::create_dir_all(&directory).is_err() || fs::write(&input_path, contents).is_err() {
+        return Err(TransformError::CommandNotRun);
+    }
+
+    // Run the command, then read the output file and clean up the temporary directory
+    let result = run_compiler(command, &input_path, &output_path)
+        .and_then(|_| fs::read(&output_path).map_err(|_| TransformError::NoOutput));
+    let _ = fs::remove_dir_all(&directory);
+    result
+}
+
+/// Run the given compiler command for the given input and output filepaths
+fn run_compiler(
+    command: &str,
+    input_path: &Path,
+    output_path: &Path,
+) -> Result<(), TransformError> {
+    let input = input_path.to_string_lossy();
+    let output = output_path.to_string_lossy();
+    let mut arguments = command.split_whitespace().map(|argument| {
+        argument
+            .replace(INPUT_PLACEHOLDER, &input)
+            .replace(OUTPUT_PLACEHOLDER, &output)
+    });
+    let Some(program) = arguments.next() else {
+        return Err(TransformError::NoCommand);
+    };
+    let status = Command::new(program)
+        .args(arguments)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(TransformError::CommandFailed),
+        Err(_) => Err(TransformError::CommandNotRun),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    /// Tests normalizing line endings, leaving contents that are not text unchanged
+    #[test]
+    fn normalize_line_endings() {
+        let transform = Transform::NormalizeLineEndings;
+        let path = Path::new("notes.txt");
+        let text = transform
+            .apply(path, b"one\r\ntwo\r\n".to_vec())
+            .expect("Could not normalize line endings");
+        assert_eq!(text, b"one\ntwo\n");
+        let binary = vec![0xff, b'\r', b'\n'];
+        let unchanged = transform
+            .apply(path, binary.clone())
+            .expect("Could not normalize line endings");
+        assert_eq!(unchanged, binary);
+    }
+
+    /// Tests stripping comments and docstrings from Python source code
+    #[test]
+    fn strip_python_comments() {
+        let source = concat!(
+            "\"\"\"Module docstring\n",
+            "over two lines\"\"\"\n",
+            "import board  # The board module\n",
+            "\n",
+            "def blink(pin):\n",
+            "    'Blink the pin'\n",
+            "    # Toggle the value\n",
+            "    text = \"# not a comment\"\n",
+            "    return (\n",
+            "        \"kept\"\n",
+            "    ), r'\\'' \\\n",
+            "        + text\n",
+        );
+        let expected = concat!(
+            "pass\n",
+            "\n",
+            "import board\n",
+            "\n",
+            "def blink(pin):\n",
+            "    pass\n",
+            "\n",
+            "    text = \"# not a comment\"\n",
+            "    return (\n",
+            "        \"kept\"\n",
+            "    ), r'\\'' \\\n",
+            "        + text\n",
+        );
+        assert_eq!(super::strip_python_comments(source), expected);
+    }
+
+    /// Tests which files transforms apply to
+    #[test]
+    fn applies_to() {
+        let compile = Transform::compile_python("mpy-cross {input} -o {output}");
+        assert!(compile.applies_to(Path::new("lib/module.py")));
+        assert!(!compile.applies_to(Path::new("code.py")));
+        assert!(compile.applies_to(Path::new("lib/pkg/main.py")));
+        assert!(compile.applies_to(Path::new("lib/foo/code.py")));
+        assert!(!compile.applies_to(Path::new("image.bmp")));
+        assert!(Transform::StripComments.applies_to(Path::new("code.py")));
+        assert!(!Transform::StripComments.applies_to(Path::new("notes.txt")));
+        assert!(Transform::NormalizeLineEndings.applies_to(Path::new("notes.txt")));
+    }
+
+    /// Tests getting the output filepaths of transforms
+    #[test]
+    fn output_path() {
+        let transforms = [
+            Transform::StripComments,
+            Transform::compile_python("mpy-cross {input} -o {output}"),
+        ];
+        assert_eq!(
+            super::output_path(&transforms, Path::new("lib/module.py")),
+            PathBuf::from("lib/module.mpy")
+        );
+        assert_eq!(
+            super::output_path(&transforms, Path::new("code.py")),
+            PathBuf::from("code.py")
+        );
+        assert!(any_applies(&transforms, Path::new("code.py")));
+        assert!(!any_applies(&transforms, Path::new("image.bmp")));
+    }
+
+    /// Tests compiling files using an external command
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn compile() {
+        // Use a command that copies the input to the output as a stand-in compiler
+        let transform = Transform::compile_python("cp {input} {output}");
+        let contents = transform
+            .apply(Path::new("module.py"), b"print('hello')".to_vec())
+            .expect("Could not compile the file");
+        assert_eq!(contents, b"print('hello')");
+
+        // Check the errors for commands that fail or cannot be run
+        let failing = Transform::compile_python("false");
+        let missing = Transform::compile_python("does-not-exist {input}");
+        let empty = Transform::compile_python("");
+        let path = Path::new("module.py");
+        assert_eq!(
+            failing.apply(path, Vec::new()),
+            Err(TransformError::CommandFailed)
+        );
+        assert_eq!(
+            missing.apply(path, Vec::new()),
+            Err(TransformError::CommandNotRun)
+        );
+        assert_eq!(
+            empty.apply(path, Vec::new()),
+            Err(TransformError::NoCommand)
+        );
+    }
+}
@@ -0,0 +1,198 @@
+// SPDX-FileCopyrightText: 2025 Alec Delaney
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+use notify::event::{AccessKind, AccessMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::monitor::FileMonitor;
+
+/// How the server finds out about changes to the source files of file monitors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WatchBackend {
+    /// Use file change notifications from the operating system, periodically re-scanning all
+    /// of the source files as well
+    #[default]
+    Notify,
+    /// Re-scan all of the source files continuously
+    Poll,
+}
+
+/// The source file changes reported since they were last taken
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceChanges {
+    /// The paths that changed
+    Paths(Vec<PathBuf>),
+    /// Changes may have been missed, so all of the source files need to be re-scanned
+    Rescan,
+}
+
+impl SourceChanges {
+    /// Gets the changed paths within the given watched directories, given whether their
+    /// subdirectories are watched too, or None if all of the source files need to be re-scanned
+    pub fn within(&self, roots: &[(PathBuf, bool)]) -> Option<Vec<PathBuf>> {
+        match self {
+            SourceChanges::Paths(paths) => Some(
+                paths
+                    .iter()
+                    .filter(|path| {
+                        roots.iter().any(|(root, recursive)| {
+                            *path == root
+                                || path.parent() == Some(root.as_path())
+                                || (*recursive && path.starts_with(root))
+                        })
+                    })
+                    .cloned()
+                    .collect(),
+            ),
+            SourceChanges::Rescan => None,
+        }
+    }
+}
+
+/// Watches the source files of file monitors for changes, using file change notifications from
+/// the operating system
+pub struct SourceWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    pending: Vec<notify::Result<Event>>,
+    watched: HashMap<PathBuf, RecursiveMode>,
+}
+
+impl SourceWatcher {
+    /// Creates a new source watcher, not watching any directories yet
+    pub fn new() -> notify::Result<Self> {
+        let (sender, events) = channel();
+        let watcher = notify::recommended_watcher(sender)?;
+        Ok(SourceWatcher {
+            watcher,
+            events,
+            pending: Vec::new(),
+            watched: HashMap::new(),
+        })
+    }
+
+    /// Watches the directories containing the source files of the given file monitors, and
+    /// stops watching any directories no longer needed
+    ///
+    /// Returns whether all of the directories of each file monitor are being watched, as
+    /// directories that do not exist cannot be watched.
+    pub fn watch_monitors(&mut self, monitors: &[FileMonitor]) -> Vec<bool> {
+        // Get the directories to watch, watching recursively if any file monitor needs it
+        let mut roots: HashMap<PathBuf, RecursiveMode> = HashMap::new();
+        for (root, recursive) in monitors.iter().flat_map(FileMonitor::get_watch_roots) {
+            let mode = roots.entry(root).or_insert(RecursiveMode::NonRecursive);
+            if recursive {
+                *mode = RecursiveMode::Recursive;
+            }
+        }
+
+        // Stop watching directories that are no longer needed, or need a different mode
+        let stale: Vec<PathBuf> = self
+            .watched
+            .iter()
+            .filter(|(root, mode)| roots.get(*root) != Some(mode))
+            .map(|(root, _)| root.clone())
+            .collect();
+        for root in stale {
+            let _ = self.watcher.unwatch(&root);
+            self.watched.remove(&root);
+        }
+
+        // Watch the new directories
+        for (root, mode) in roots {
+            if !self.watched.contains_key(&root) && self.watcher.watch(&root, mode).is_ok() {
+                self.watched.insert(root, mode);
+            }
+        }
+
+        monitors
+            .iter()
+            .map(|monitor| {
+                monitor
+                    .get_watch_roots()
+                    .iter()
+                    .all(|(root, _)| self.watched.contains_key(root))
+            })
+            .collect()
+    }
+
+    /// Waits until a source file change is reported or the given timeout passes, keeping the
+    /// change to be taken with the others
+    pub fn wait_for_changes(&mut self, timeout: Duration) {
+        if let Ok(result) = self.events.recv_timeout(timeout) {
+            self.pending.push(result);
+        }
+    }
+
+    /// Takes the source file changes reported since this was last called
+    pub fn take_changes(&mut self) -> SourceChanges {
+        let mut paths = Vec::new();
+        for result in self.pending.drain(..).chain(self.events.try_iter()) {
+            let event = match result {
+                Ok(event) if !event.need_rescan() => event,
+                _ => return SourceChanges::Rescan,
+            };
+
+            // Ignore files being opened or read, such as when they are copied to boards
+            if let EventKind::Access(kind) = event.kind {
+                if kind != AccessKind::Close(AccessMode::Write) {
+                    continue;
+                }
+            }
+            for path in event.paths {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        SourceChanges::Paths(paths)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::fs;
+    use std::path::Path;
+    use std::time::Instant;
+    use tempfile::TempDir;
+
+    /// Tests that changes to the source files of a file monitor are reported
+    #[test]
+    fn take_changes() {
+        // Watch a file monitor for files in a temporary directory
+        let directory = TempDir::new().expect("Could not create temporary directory");
+        let base_directory =
+            fs::canonicalize(directory.path()).expect("Could not resolve temporary directory");
+        let monitor = FileMonitor::new(&["*.py"], Path::new("/write"), &base_directory);
+        let mut watcher = SourceWatcher::new().expect("Could not create watcher");
+        assert_eq!(watcher.watch_monitors(&[monitor]), vec![true]);
+
+        // Write a source file and wait for the change to be reported
+        let path = base_directory.join("code.py");
+        fs::write(&path, "print('hello')").expect("Could not write file");
+        let start = Instant::now();
+        let mut changed = Vec::new();
+        while !changed.contains(&path) && start.elapsed() < Duration::from_secs(5) {
+            watcher.wait_for_changes(Duration::from_millis(10));
+            let changes = watcher.take_changes();
+            changed.extend(
+                changes
+                    .within(&[(base_directory.clone(), false)])
+                    .expect("Changes were missed"),
+            );
+        }
+        assert!(changed.contains(&path));
+
+        // Check that file monitors whose directories do not exist are not watched
+        let missing = FileMonitor::new(&["*.py"], Path::new("/write"), &base_directory.join("no"));
+        assert_eq!(watcher.watch_monitors(&[missing]), vec![false]);
+    }
+}
@@ -0,0 +1,441 @@
+// SPDX-FileCopyrightText: 2025 Alec Delaney
+// SPDX-License-Identifier: MIT
+
+use crate::filetree::get_credentials_file;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Component, Path};
+use std::time::Duration;
+
+/// The URL scheme used by the web workflow
+const HTTP_SCHEME: &str = "http://";
+
+/// The route of the web workflow file system API
+const FS_ROUTE: &str = "/fs";
+
+/// The characters to percent-encode in the paths of web workflow URLs
+const PATH_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// The timeout for connecting to, reading from and writing to the board
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Web workflow request errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebWorkflowError {
+    /// The URL is not a valid web workflow URL
+    InvalidUrl,
+    /// The board could not be connected to
+    ConnectionFailed,
+    /// The response from the board could not be parsed
+    BadResponse,
+    /// The password was not accepted by the board
+    Unauthorized,
+    /// The board file system is read-only, usually because it is connected over USB
+    ReadOnly,
+    /// The file or directory does not exist on the board
+    NotFound,
+    /// The board does not have enough space for the file
+    InsufficientStorage,
+    /// The board responded with an unexpected status code
+    UnexpectedStatus(u16),
+}
+
+impl fmt::Display for WebWorkflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebWorkflowError::InvalidUrl => write!(f, "invalid web workflow URL"),
+            WebWorkflowError::ConnectionFailed => write!(f, "could not connect to the board"),
+            WebWorkflowError::BadResponse => write!(f, "could not parse the board response"),
+            WebWorkflowError::Unauthorized => write!(f, "the password was not accepted"),
+            WebWorkflowError::ReadOnly => write!(f, "the board file system is read-only"),
+            WebWorkflowError::NotFound => write!(f, "the file does not exist on the board"),
+            WebWorkflowError::InsufficientStorage => write!(f, "the board is out of space"),
+            WebWorkflowError::UnexpectedStatus(status) => {
+                write!(f, "the board responded with status {status}")
+            }
+        }
+    }
+}
+
+/// Connection information for a board using the CircuitPython web workflow
+///
+/// Files are written using the REST API of the web workflow, rather than a local
+/// file system.  The password is never serialized, so it is not saved in workspaces
+/// or sent to clients, and is kept in the credential store instead.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct WebWorkflow {
+    /// The base URL of the board, such as http://circuitpython.local
+    pub url: String,
+    /// The web workflow password, from CIRCUITPY_WEB_API_PASSWORD in settings.toml, which
+    /// is still read from workspaces saved by previous versions
+    #[serde(default, skip_serializing)]
+    pub password: String,
+}
+
+impl WebWorkflow {
+    /// Creates the web workflow connection information, given the base URL and password
+    pub fn new(url: &str, password: &str) -> Result<Self, WebWorkflowError> {
+        // Assume HTTP if no scheme is given, as that is all the web workflow supports
+        let url = if url.contains("://") {
+            url.to_owned()
+        } else {
+            format!("{HTTP_SCHEME}{url}")
+        };
+        let web_workflow = WebWorkflow {
+            url: url.trim_end_matches('/').to_owned(),
+            password: password.to_owned(),
+        };
+
+        // Check that the host can be parsed from the URL
+        web_workflow.host()?;
+        Ok(web_workflow)
+    }
+
+    /// Get the host (and port, if given) of the board from the URL
+    fn host(&self) -> Result<&str, WebWorkflowError> {
+        match self.url.strip_prefix(HTTP_SCHEME) {
+            Some(host) if !host.is_empty() && !host.contains('/') => Ok(host),
+            _ => Err(WebWorkflowError::InvalidUrl),
+        }
+    }
+
+    /// Saves the password in the credential store in the application directory, keyed by the
+    /// URL of the board, so that it can be loaded by the server
+    pub fn store_password(&self) -> io::Result<()> {
+        let mut credentials = read_credentials();
+        credentials.insert(self.url.clone(), self.password.clone());
+        let contents = serde_json::to_string_pretty(&credentials)?;
+
+        // Only allow the current user to read the credential store
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(get_credentials_file())?
+            .write_all(contents.as_bytes())
+    }
+
+    /// Loads the password from the credential store, unless it is already known
+    pub fn load_password(&mut self) {
+        if !self.password.is_empty() {
+            return;
+        }
+        if let Some(password) = read_credentials().remove(&self.url) {
+            self.password = password;
+        }
+    }
+
+    /// Get the URL for the given path on the board, for display
+    pub fn display_url(&self, path: &Path) -> String {
+        format!("{}{}", self.url, board_path_str(path))
+    }
+
+    /// Send a request to the board with the given additional headers, returning the status
+    /// code of the response
+    fn request(
+        &self,
+        method: &str,
+        route: &str,
+        body: &[u8],
+        extra_headers: &[(&str, String)],
+    ) -> Result<u16, WebWorkflowError> {
+        // Connect to the board
+        let host = self.host()?;
+        let address = match host.to_socket_addrs() {
+            Ok(mut addresses) => addresses.next().ok_or(WebWorkflowError::ConnectionFailed)?,
+            Err(_) => match (host, 80).to_socket_addrs() {
+                Ok(mut addresses) => addresses.next().ok_or(WebWorkflowError::ConnectionFailed)?,
+                Err(_) => return Err(WebWorkflowError::ConnectionFailed),
+            },
+        };
+        let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)
+            .map_err(|_| WebWorkflowError::ConnectionFailed)?;
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .expect("Bad duration passed as socket read timeout");
+        stream
+            .set_write_timeout(Some(TIMEOUT))
+            .expect("Bad duration passed as socket write timeout");
+
+        // Create the request headers, using basic authentication with an empty username
+        let credentials = BASE64_STANDARD.encode(format!(":{}", self.password));
+        let mut headers = format!(
+            "{method} {route} HTTP/1.1\r\n\
+             Host: {host}\r\n\
+             Authorization: Basic {credentials}\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n",
+            body.len()
+        );
+        for (name, value) in extra_headers {
+            headers.push_str(&format!("{name}: {value}\r\n"));
+        }
+        headers.push_str("\r\n");
+
+        // Send the request
+        if stream.write_all(headers.as_bytes()).is_err() || stream.write_all(body).is_err() {
+            return Err(WebWorkflowError::ConnectionFailed);
+        }
+
+        // Read the status code from the response, and then the rest of the response
+        let mut reader = BufReader::new(stream);
+        let mut status_line = String::new();
+        if reader.read_line(&mut status_line).is_err() {
+            return Err(WebWorkflowError::BadResponse);
+        }
+        let _ = reader.read_to_end(&mut Vec::new());
+        match status_line.split_whitespace().nth(1) {
+            Some(status) => status.parse().map_err(|_| WebWorkflowError::BadResponse),
+            None => Err(WebWorkflowError::BadResponse),
+        }
+    }
+
+    /// Write the given contents to the file at the given path on the board, optionally
+    /// setting its modification time (in milliseconds since the Unix epoch)
+    pub fn put_file(
+        &self,
+        path: &Path,
+        contents: &[u8],
+        timestamp: Option<u128>,
+    ) -> Result<(), WebWorkflowError> {
+        let route = fs_route(path, false);
+        let headers: Vec<_> = timestamp
+            .map(|timestamp| ("X-Timestamp", timestamp.to_string()))
+            .into_iter()
+            .collect();
+        let status = self.request("PUT", &route, contents, &headers)?;
+        check_status(status, &[200, 201, 204])
+    }
+
+    /// Create the directory at the given path on the board, if it does not already exist
+    pub fn make_directory(&self, path: &Path) -> Result<(), WebWorkflowError> {
+        let route = fs_route(path, true);
+        let status = self.request("PUT", &route, &[], &[])?;
+        check_status(status, &[200, 201, 204, 409])
+    }
+
+    /// Delete the file at the given path on the board
+    pub fn delete(&self, path: &Path) -> Result<(), WebWorkflowError> {
+        let route = fs_route(path, false);
+        let status = self.request("DELETE", &route, &[], &[])?;
+        check_status(status, &[200, 204])
+    }
+
+    /// Move the file at the given path on the board to the given destination path on the board
+    pub fn move_file(&self, path: &Path, destination: &Path) -> Result<(), WebWorkflowError> {
+        let route = fs_route(path, false);
+        let headers = [("X-Destination", fs_route(destination, false))];
+        let status = self.request("MOVE", &route, &[], &headers)?;
+        check_status(status, &[200, 201, 204])
+    }
+}
+
+/// Read the web workflow passwords from the credential store, keyed by the URLs of the boards
+fn read_credentials() -> BTreeMap<String, String> {
+    fs::read_to_string(get_credentials_file())
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Convert a path on the board to a string using forward slashes, regardless of platform
+fn board_path_str(path: &Path) -> String {
+    let mut path_str = String::new();
+    for component in path.components() {
+        if let Component::Normal(name) = component {
+            path_str.push('/');
+            path_str.push_str(&name.to_string_lossy());
+        }
+    }
+    path_str
+}
+
+/// Get the web workflow file system route for the given path on the board
+fn fs_route(path: &Path, is_directory: bool) -> String {
+    let mut route = String::from(FS_ROUTE);
+    for component in path.components() {
+        if let Component::Normal(name) = component {
+            route.push('/');
+            route.extend(utf8_percent_encode(
+                &name.to_string_lossy(),
+                PATH_ENCODE_SET,
+            ));
+        }
+    }
+    if is_directory || route == FS_ROUTE {
+        route.push('/');
+    }
+    route
+}
+
+/// Check that the status code of a response is one of the expected status codes
+fn check_status(status: u16, expected: &[u16]) -> Result<(), WebWorkflowError> {
+    if expected.contains(&status) {
+        return Ok(());
+    }
+    Err(match status {
+        401 => WebWorkflowError::Unauthorized,
+        403 => WebWorkflowError::ReadOnly,
+        404 => WebWorkflowError::NotFound,
+        507 => WebWorkflowError::InsufficientStorage,
+        _ => WebWorkflowError::UnexpectedStatus(status),
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::test_support::WebWorkflowStandIn;
+
+    /// Tests creating web workflow connection information from URLs
+    #[test]
+    fn new() {
+        let web = WebWorkflow::new("cpy-123456.local", "pass").expect("Could not parse URL");
+        assert_eq!(web.url, "http://cpy-123456.local");
+        let web = WebWorkflow::new("http://127.0.0.1:8080/", "pass").expect("Could not parse URL");
+        assert_eq!(web.url, "http://127.0.0.1:8080");
+        assert_eq!(web.host(), Ok("127.0.0.1:8080"));
+
+        let error = WebWorkflow::new("https://cpy-123456.local", "pass").unwrap_err();
+        assert_eq!(error, WebWorkflowError::InvalidUrl);
+        let error = WebWorkflow::new("http://cpy-123456.local/fs", "pass").unwrap_err();
+        assert_eq!(error, WebWorkflowError::InvalidUrl);
+    }
+
+    /// Tests that the password is read from previously saved workspaces, but never serialized
+    #[test]
+    fn serialize_password() {
+        let web = WebWorkflow::new("cpy-123456.local", "secret").expect("Could not parse URL");
+        let json = serde_json::to_value(&web).expect("Could not serialize");
+        assert_eq!(json, serde_json::json!({"url": "http://cpy-123456.local"}));
+
+        let json = r#"{"url": "http://cpy-123456.local", "password": "secret"}"#;
+        let web: WebWorkflow = serde_json::from_str(json).expect("Could not deserialize");
+        assert_eq!(web.password, "secret");
+    }
+
+    /// Tests storing the password in the credential store and loading it again
+    #[test]
+    #[serial_test::serial]
+    fn store_password() {
+        // Save the current state of the application directory
+        let preexisted = crate::test_support::save_app_directory();
+
+        // Store the password, and load it for connection information without one
+        let web = WebWorkflow::new("cpy-123456.local", "secret").expect("Could not parse URL");
+        web.store_password().expect("Could not store the password");
+        let mut loaded = WebWorkflow::new("cpy-123456.local", "").expect("Could not parse URL");
+        loaded.load_password();
+        let mut other = WebWorkflow::new("cpy-654321.local", "").expect("Could not parse URL");
+        other.load_password();
+
+        // Restore the previous application directory if it existed
+        crate::test_support::restore_app_directory(preexisted);
+
+        // Check that only the password for the same board was loaded
+        assert_eq!(loaded.password, "secret");
+        assert!(other.password.is_empty());
+    }
+
+    /// Tests creating file system routes from paths on the board
+    #[test]
+    fn fs_route() {
+        assert_eq!(super::fs_route(Path::new("/code.py"), false), "/fs/code.py");
+        assert_eq!(
+            super::fs_route(Path::new("/lib/my lib/a.mpy"), false),
+            "/fs/lib/my%20lib/a.mpy"
+        );
+        assert_eq!(super::fs_route(Path::new("/lib"), true), "/fs/lib/");
+        assert_eq!(super::fs_route(Path::new("/"), true), "/fs/");
+    }
+
+    /// Tests writing a file to the board
+    #[test]
+    fn put_file() {
+        let stand_in = WebWorkflowStandIn::start(|_| 201);
+        let web = WebWorkflow::new(&stand_in.url(), "secret").expect("Could not parse URL");
+        web.put_file(Path::new("/lib/test.py"), b"print('hi')", Some(1234))
+            .expect("Could not write the file");
+
+        let requests = stand_in.requests();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.route, "/fs/lib/test.py");
+        assert_eq!(request.body, b"print('hi')");
+        assert!(request.headers.contains(&format!(
+            "Authorization: Basic {}",
+            BASE64_STANDARD.encode(":secret")
+        )));
+        assert!(request.headers.contains("X-Timestamp: 1234"));
+    }
+
+    /// Tests deleting a file from the board
+    #[test]
+    fn delete() {
+        let stand_in = WebWorkflowStandIn::start(|_| 204);
+        let web = WebWorkflow::new(&stand_in.url(), "secret").expect("Could not parse URL");
+        web.delete(Path::new("/code.py"))
+            .expect("Could not delete the file");
+
+        let requests = stand_in.requests();
+        assert_eq!(requests[0].method, "DELETE");
+        assert_eq!(requests[0].route, "/fs/code.py");
+    }
+
+    /// Tests moving a file on the board
+    #[test]
+    fn move_file() {
+        let stand_in = WebWorkflowStandIn::start(|_| 201);
+        let web = WebWorkflow::new(&stand_in.url(), "secret").expect("Could not parse URL");
+        web.move_file(Path::new("/code.py"), Path::new("/.trash/code.py"))
+            .expect("Could not move the file");
+
+        let requests = stand_in.requests();
+        assert_eq!(requests[0].method, "MOVE");
+        assert_eq!(requests[0].route, "/fs/code.py");
+        assert!(requests[0]
+            .headers
+            .contains("X-Destination: /fs/.trash/code.py"));
+    }
+
+    /// Tests the errors returned for unsuccessful status codes
+    #[test]
+    fn status_errors() {
+        let stand_in = WebWorkflowStandIn::start(|request| match request.route.as_str() {
+            "/fs/unauthorized.py" => 401,
+            "/fs/readonly.py" => 403,
+            "/fs/full.py" => 507,
+            _ => 500,
+        });
+        let web = WebWorkflow::new(&stand_in.url(), "secret").expect("Could not parse URL");
+        let put = |path: &str| web.put_file(Path::new(path), b"", None).unwrap_err();
+        assert_eq!(put("/unauthorized.py"), WebWorkflowError::Unauthorized);
+        assert_eq!(put("/readonly.py"), WebWorkflowError::ReadOnly);
+        assert_eq!(put("/full.py"), WebWorkflowError::InsufficientStorage);
+        assert_eq!(put("/other.py"), WebWorkflowError::UnexpectedStatus(500));
+    }
+
+    /// Tests that an existing directory is not treated as an error
+    #[test]
+    fn make_directory() {
+        let stand_in = WebWorkflowStandIn::start(|_| 409);
+        let web = WebWorkflow::new(&stand_in.url(), "secret").expect("Could not parse URL");
+        web.make_directory(Path::new("/lib"))
+            .expect("Could not create the directory");
+        assert_eq!(stand_in.requests()[0].route, "/fs/lib/");
+    }
+}
@@ -235,7 +235,7 @@ mod test {
     fn get_monitor() -> FileMonitor {
         let write_directory = TempDir::new().expect("Could not create temporary write directory");
         let base_directory = TempDir::new().expect("Could not create temporary base directory");
-        FileMonitor::new("test*", write_directory.path(), base_directory.path())
+        FileMonitor::new(&["test*"], write_directory.path(), base_directory.path())
     }
 
     /// Helper function for creating a workspace
@@ -284,7 +284,7 @@ mod test {
             // Generate the expected contained file monitor
             let write_directory = PathBuf::from("/circpush/tests/assets/sandbox/");
             let base_directory = PathBuf::from("/circpush");
-            let monitor = FileMonitor::new("test*", &write_directory, &base_directory);
+            let monitor = FileMonitor::new(&["test*"], &write_directory, &base_directory);
 
             // Generate the expected workspace fields
             let expected_desc = "A test workspace";